@@ -0,0 +1,40 @@
+use std::fs;
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use minigrep::encoding;
+use minigrep::input::{self, MmapChoice};
+
+/* Writes a file of about 64 MiB with one matching line in every thousand, and compares
+searching it through a memory map against reading it into a buffer first. */
+fn large_file(c: &mut Criterion) {
+    let path = std::env::temp_dir().join("minigrep-bench-input.txt");
+    let mut contents = String::new();
+    for i in 0..1_000_000 {
+        if i % 1000 == 0 {
+            contents.push_str("the needle we are looking for is right here in this line\n");
+        } else {
+            contents.push_str("just a regular line of text without anything interesting\n");
+        }
+    }
+    fs::write(&path, contents).unwrap();
+    let path = path.to_str().unwrap();
+
+    let mut group = c.benchmark_group("large_file");
+    group.sample_size(20);
+    for (name, choice) in [("mmap", MmapChoice::Always), ("read", MmapChoice::Never)] {
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                let bytes = input::open(path, choice).unwrap();
+                let decoded = encoding::decode(&bytes, None).unwrap();
+                black_box(minigrep::search("needle", &decoded.text).len())
+            })
+        });
+    }
+    group.finish();
+
+    fs::remove_file(path).unwrap();
+}
+
+criterion_group!(benches, large_file);
+criterion_main!(benches);
@@ -0,0 +1,32 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use minigrep::encoding;
+use minigrep::matcher::{Literal, Matcher};
+
+/* The input is a file. The first byte picks an encoding, the next four are the query and the
+rest is the contents, so the decoder and the matchers see every kind of bytes. */
+const LABELS: &[Option<&str>] = &[None, Some("utf-8"), Some("utf-16le"), Some("utf-16be"), Some("latin1")];
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&pick, rest)) = data.split_first() else {
+        return;
+    };
+    let (query, contents) = rest.split_at(rest.len().min(4));
+    let query = String::from_utf8_lossy(query);
+    let label = LABELS[pick as usize % LABELS.len()].map(|label| encoding::parse_label(label).unwrap());
+
+    let Ok(decoded) = encoding::decode(contents, label) else {
+        return;
+    };
+    for ignore_case in [false, true] {
+        let matcher = Literal::new(&query, ignore_case);
+        for line in decoded.text.lines() {
+            for range in matcher.find_iter(line).unwrap() {
+                assert!(line.is_char_boundary(range.start) && line.is_char_boundary(range.end));
+            }
+        }
+    }
+    minigrep::search(&query, &decoded.text);
+    minigrep::search_case_insensitive(&query, &decoded.text);
+});
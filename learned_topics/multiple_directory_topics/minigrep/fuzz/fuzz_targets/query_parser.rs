@@ -0,0 +1,20 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use minigrep::matcher::{self, Engine};
+use minigrep::Config;
+
+/* The input is a command line, with a NUL byte between the arguments. Parsing it may fail,
+but it should never panic, and neither should building the matcher for whatever query
+came out of it. */
+fuzz_target!(|data: &[u8]| {
+    let Ok(text) = std::str::from_utf8(data) else {
+        return;
+    };
+    let args: Vec<String> = std::iter::once("minigrep").chain(text.split('\0')).map(String::from).collect();
+    if let Ok(config) = Config::build(&args) {
+        for engine in [Engine::Literal, Engine::Regex, Engine::Backtracking { limit: 10_000 }] {
+            let _ = matcher::build(&config.query, config.ignore_case, engine);
+        }
+    }
+});
@@ -0,0 +1,62 @@
+use std::collections::HashMap;
+
+use serde_json::json;
+
+/* How often every value of the --group-by capture was seen, like `sort | uniq -c` but
+without losing which capture the value came from. */
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Histogram {
+    counts: HashMap<String, usize>,
+}
+
+impl Histogram {
+    pub fn add(&mut self, value: String) {
+        *self.counts.entry(value).or_insert(0) += 1;
+    }
+
+    /* A value seen by both histograms ends up with the sum of both counts. */
+    pub fn merge(&mut self, other: Histogram) {
+        for (value, count) in other.counts {
+            *self.counts.entry(value).or_insert(0) += count;
+        }
+    }
+
+    /* The most common values first, values seen equally often in alphabetical order. */
+    pub fn rows(&self, top: Option<usize>) -> Vec<(&str, usize)> {
+        let mut rows: Vec<(&str, usize)> = self.counts.iter().map(|(value, &count)| (value.as_str(), count)).collect();
+        rows.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        rows.truncate(top.unwrap_or(rows.len()));
+        rows
+    }
+
+    /* Counts right aligned in front of the values, or one JSON object for every row. */
+    pub fn format(&self, top: Option<usize>, json: bool) -> String {
+        let rows = self.rows(top);
+        let width = rows.iter().map(|(_, count)| count.to_string().len()).max().unwrap_or(0);
+        rows.iter()
+            .map(|(value, count)| match json {
+                true => format!("{}\n", json!({ "value": value, "count": count })),
+                false => format!("{count:>width$} {value}\n"),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn most_common_first_and_ties_alphabetical() {
+        let mut histogram = Histogram::default();
+        for value in ["500", "200", "404", "200"] {
+            histogram.add(value.to_string());
+        }
+        let mut other = Histogram::default();
+        other.add("404".to_string());
+        histogram.merge(other);
+
+        assert_eq!(vec![("200", 2), ("404", 2), ("500", 1)], histogram.rows(None));
+        assert_eq!("2 200\n", histogram.format(Some(1), false));
+    }
+}
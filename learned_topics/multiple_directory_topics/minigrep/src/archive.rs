@@ -0,0 +1,122 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, Read};
+
+use flate2::read::GzDecoder;
+
+/* Only the file name decides whether something is an archive, minigrep does not sniff the
+contents. */
+pub fn is_archive(path: &str) -> bool {
+    [".tar", ".tar.gz", ".tgz", ".zip"]
+        .iter()
+        .any(|extension| path.ends_with(extension))
+}
+
+/* Calls search once for every regular file inside the archive, with the path of the member
+inside the archive and its uncompressed bytes. Directories and links are skipped, and so are
+members larger than max_size bytes, like --max-filesize skips files. */
+pub fn for_each_member(
+    path: &str,
+    max_size: Option<u64>,
+    mut search: impl FnMut(&str, &[u8]) -> Result<(), Box<dyn Error>>,
+) -> Result<(), Box<dyn Error>> {
+    let file = File::open(path)?;
+
+    if path.ends_with(".zip") {
+        let mut archive = zip::ZipArchive::new(file)?;
+        for index in 0..archive.len() {
+            let mut member = archive.by_index(index)?;
+            if !member.is_file() {
+                continue;
+            }
+            let name = member.name()?.to_string();
+            if max_size.is_some_and(|max| member.size() > max) {
+                continue;
+            }
+            if let Some(bytes) = read(&mut member, max_size)? {
+                search(&name, &bytes)?;
+            }
+        }
+        return Ok(());
+    }
+
+    /* A .tar.gz is just a .tar that has to be unzipped while it is read. */
+    let reader: Box<dyn Read> = if path.ends_with(".tar") {
+        Box::new(file)
+    } else {
+        Box::new(GzDecoder::new(file))
+    };
+    let mut archive = tar::Archive::new(reader);
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let name = entry.path()?.display().to_string();
+        if max_size.is_some_and(|max| entry.header().size().unwrap_or(0) > max) {
+            continue;
+        }
+        if let Some(bytes) = read(&mut entry, max_size)? {
+            search(&name, &bytes)?;
+        }
+    }
+
+    Ok(())
+}
+
+/* The sizes in the headers come from the archive and can lie, so a member is never read past
+max_size either. None when it turns out to be larger after all. */
+fn read(member: &mut impl Read, max_size: Option<u64>) -> io::Result<Option<Vec<u8>>> {
+    let mut bytes = Vec::new();
+    match max_size {
+        Some(max) => {
+            member.take(max + 1).read_to_end(&mut bytes)?;
+            Ok(Some(bytes).filter(|bytes| bytes.len() as u64 <= max))
+        }
+        None => {
+            member.read_to_end(&mut bytes)?;
+            Ok(Some(bytes))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_every_member_of_a_tar() {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (name, contents) in [("a.txt", "alpha\n"), ("dir/b.txt", "beta\n")] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            builder.append_data(&mut header, name, contents.as_bytes()).unwrap();
+        }
+        let path = std::env::temp_dir().join(format!("minigrep-archive-test-{}.tar", std::process::id()));
+        std::fs::write(&path, builder.into_inner().unwrap()).unwrap();
+
+        let mut members = Vec::new();
+        for_each_member(path.to_str().unwrap(), None, |name, bytes| {
+            members.push((name.to_string(), String::from_utf8(bytes.to_vec())?));
+            Ok(())
+        })
+        .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            vec![
+                ("a.txt".to_string(), "alpha\n".to_string()),
+                ("dir/b.txt".to_string(), "beta\n".to_string())
+            ],
+            members
+        );
+    }
+
+    #[test]
+    fn knows_archives_by_name() {
+        assert!(is_archive("build.tar.gz"));
+        assert!(is_archive("build.zip"));
+        assert!(!is_archive("notes.txt"));
+    }
+}
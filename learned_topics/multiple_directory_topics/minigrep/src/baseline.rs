@@ -0,0 +1,185 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex};
+
+use serde_json::{json, Value};
+
+/* One matching line, as --json prints it. The path and the text are what make two matches the
+same one, the line number only says where it is now. */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Match {
+    pub path: String,
+    pub line: usize,
+    pub text: String,
+}
+
+/* How a match changed since the baseline. A moved match has the same path and text, and its
+line number in the baseline. */
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    New,
+    Removed,
+    Moved { from: usize },
+}
+
+/* The changes between the baseline and this run, sorted by path and line. Matches that are
+still on the same line are left out. */
+#[derive(Debug, Default, PartialEq)]
+pub struct Diff {
+    pub changes: Vec<(Change, Match)>,
+}
+
+/* Reads the output of an earlier `minigrep --json` run. Rows that are not matching lines, like
+the counts of --count-matches or the line of --stats-json, are skipped. */
+pub fn read(path: &str) -> Result<Vec<Match>, Box<dyn Error>> {
+    let text = fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+    parse(&text).map_err(|e| format!("{path}: {e}").into())
+}
+
+pub fn parse(text: &str) -> Result<Vec<Match>, String> {
+    let mut matches = Vec::new();
+    for (index, row) in text.lines().enumerate() {
+        if row.trim().is_empty() {
+            continue;
+        }
+        let row: Value = serde_json::from_str(row).map_err(|e| format!("line {}: {e}", index + 1))?;
+        if let (Some(path), Some(line), Some(text)) = (row["path"].as_str(), row["line"].as_u64(), row["text"].as_str()) {
+            matches.push(Match { path: path.to_string(), line: line as usize, text: text.to_string() });
+        }
+    }
+    Ok(matches)
+}
+
+/* Matches with the same path and text are paired up by their order in the file. A pair on the
+same line has not changed, any other pair has moved, and whatever is left over is new or was
+removed. */
+pub fn compare(baseline: &[Match], current: &[Match]) -> Diff {
+    /* For every path and text, the lines it was on in the baseline and the lines it is on now. */
+    type Lines = (Vec<usize>, Vec<usize>);
+    let mut lines: BTreeMap<(&str, &str), Lines> = BTreeMap::new();
+    for found in baseline {
+        lines.entry((&found.path, &found.text)).or_default().0.push(found.line);
+    }
+    for found in current {
+        lines.entry((&found.path, &found.text)).or_default().1.push(found.line);
+    }
+
+    let mut changes = Vec::new();
+    for ((path, text), (mut before, mut now)) in lines {
+        let unchanged: Vec<usize> = now.iter().copied().filter(|line| before.contains(line)).collect();
+        for line in unchanged {
+            before.remove(before.iter().position(|&other| other == line).unwrap());
+            now.remove(now.iter().position(|&other| other == line).unwrap());
+        }
+        before.sort_unstable();
+        now.sort_unstable();
+
+        let at = |line: usize| Match { path: path.to_string(), line, text: text.to_string() };
+        let paired = before.len().min(now.len());
+        for (&from, &line) in before.iter().zip(&now) {
+            changes.push((Change::Moved { from }, at(line)));
+        }
+        changes.extend(now[paired..].iter().map(|&line| (Change::New, at(line))));
+        changes.extend(before[paired..].iter().map(|&line| (Change::Removed, at(line))));
+    }
+    changes.sort_by(|(_, a), (_, b)| (&a.path, a.line).cmp(&(&b.path, b.line)));
+    Diff { changes }
+}
+
+impl Diff {
+    pub fn count(&self, wanted: fn(&Change) -> bool) -> usize {
+        self.changes.iter().filter(|(change, _)| wanted(change)).count()
+    }
+
+    /* One change on every line, `+` for new, `-` for removed and `~` for moved with the line it
+    was on before, then how many of each there are. With --json every change is an object. */
+    pub fn format(&self, json: bool) -> String {
+        let mut text = String::new();
+        for (change, Match { path, line, text: line_text }) in &self.changes {
+            if json {
+                let mut row = json!({ "path": path, "line": line, "text": line_text });
+                row["change"] = match change {
+                    Change::New => json!("new"),
+                    Change::Removed => json!("removed"),
+                    Change::Moved { from } => {
+                        row["baseline_line"] = json!(from);
+                        json!("moved")
+                    }
+                };
+                text.push_str(&format!("{row}\n"));
+                continue;
+            }
+            text.push_str(&match change {
+                Change::New => format!("+ {path}:{line}:{line_text}\n"),
+                Change::Removed => format!("- {path}:{line}:{line_text}\n"),
+                Change::Moved { from } => format!("~ {path}:{from}->{line}:{line_text}\n"),
+            });
+        }
+        if !json {
+            text.push_str(&format!(
+                "{} new, {} removed, {} moved\n",
+                self.count(|change| *change == Change::New),
+                self.count(|change| *change == Change::Removed),
+                self.count(|change| matches!(change, Change::Moved { .. })),
+            ));
+        }
+        text
+    }
+}
+
+/* Where the printer writes with --baseline. The results are compared before anything is
+printed, so they are kept here instead of going to stdout. */
+#[derive(Clone, Default)]
+pub struct Collected(Arc<Mutex<Vec<u8>>>);
+
+impl Collected {
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.0.lock().unwrap()).into_owned()
+    }
+}
+
+impl Write for Collected {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(bytes);
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn found(path: &str, line: usize, text: &str) -> Match {
+        Match { path: path.to_string(), line, text: text.to_string() }
+    }
+
+    #[test]
+    fn shifted_lines_are_moved_not_new() {
+        let baseline = parse(concat!(
+            "{\"path\":\"a.rs\",\"line\":3,\"columns\":[1],\"text\":\"unsafe {\"}\n",
+            "{\"path\":\"a.rs\",\"line\":9,\"columns\":[1],\"text\":\"unsafe {\"}\n",
+            "{\"path\":\"b.rs\",\"line\":1,\"columns\":[5],\"text\":\"let x = unsafe { y };\"}\n",
+            "{\"files_searched\":2,\"matches\":3}\n",
+        ))
+        .unwrap();
+        let current = [found("a.rs", 5, "unsafe {"), found("a.rs", 9, "unsafe {"), found("a.rs", 12, "unsafe {")];
+
+        let diff = compare(&baseline, &current);
+        assert_eq!(
+            vec![
+                (Change::Moved { from: 3 }, found("a.rs", 5, "unsafe {")),
+                (Change::New, found("a.rs", 12, "unsafe {")),
+                (Change::Removed, found("b.rs", 1, "let x = unsafe { y };")),
+            ],
+            diff.changes
+        );
+        assert_eq!(1, diff.count(|change| *change == Change::New));
+        assert!(compare(&current, &current).changes.is_empty());
+    }
+}
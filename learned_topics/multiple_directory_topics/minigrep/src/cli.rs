@@ -0,0 +1,311 @@
+/* Every flag minigrep knows about. Config::build only accepts flags from this list, and the
+help text, shell completions and man page are all written from it, so they cannot drift apart.
+- value: the name of the value the flag takes, None for flags that are on or off.
+- choices: the values that are allowed, empty when anything goes. */
+pub struct Flag {
+    pub long: &'static str,
+    pub short: Option<char>,
+    pub value: Option<&'static str>,
+    pub choices: &'static [&'static str],
+    pub help: &'static str,
+}
+
+const fn switch(long: &'static str, short: Option<char>, help: &'static str) -> Flag {
+    Flag { long, short, value: None, choices: &[], help }
+}
+
+const fn option(long: &'static str, short: Option<char>, value: &'static str, help: &'static str) -> Flag {
+    Flag { long, short, value: Some(value), choices: &[], help }
+}
+
+const fn choice(long: &'static str, value: &'static str, choices: &'static [&'static str], help: &'static str) -> Flag {
+    Flag { long, short: None, value: Some(value), choices, help }
+}
+
+const SORT_KEYS: &[&str] = &["path", "modified", "accessed", "created"];
+
+pub const FLAGS: &[Flag] = &[
+    switch("help", Some('h'), "Print this help and exit"),
+    choice("generate", "KIND", &["completions-bash", "completions-zsh", "completions-fish", "man"],
+        "Print shell completions or the man page and exit"),
+    switch("regex", None, "Treat the query as a regular expression"),
+    switch("perl-regexp", Some('P'), "Treat the query as a regex with lookaround and backreferences, for a backtracking engine"),
+    option("backtrack-limit", None, "N", "With -P, give up on a line after N backtracking steps, 1000000 by default"),
+    switch("boolean", None, "Combine terms in the query with AND, OR, NOT and parentheses"),
+    switch("crlf", None, "Treat CRLF as the line ending, so the CR is not part of the line and $ matches before it"),
+    switch("block", None, "Match paragraphs instead of lines and print every line of the ones that match"),
+    choice("format", "FORMAT", &["csv", "jsonl"], "Split the input into CSV or JSON Lines records"),
+    option("field", None, "NAME[=VALUE]",
+        "Match the query against one field, or with =VALUE only keep records where the field is VALUE, and with ~=VALUE where it contains VALUE"),
+    option("jq-like", None, "PATH", "Match the query against a nested field, like .user.id"),
+    option("columns", None, "LIST", "Print these comma separated fields instead of the whole record"),
+    option("encoding", Some('E'), "LABEL", "Decode the input from this encoding, like utf-16le or latin1"),
+    switch("byte-offset", Some('b'), "Print the byte offset of every line in the original file"),
+    switch("mmap", None, "Always search files through a memory map"),
+    switch("no-mmap", None, "Never search files through a memory map"),
+    switch("stats", None, "Print statistics about the search after the results"),
+    switch("stats-json", None, "Print statistics about the search as JSON after the results"),
+    switch("search-archives", None, "Search every file inside tar, tar.gz and zip archives"),
+    option("pre", None, "COMMAND", "Search the output of COMMAND <file> instead of the file"),
+    option("pre-glob", None, "GLOB", "Only use the --pre command for files matching this glob"),
+    switch("git-changed", None, "Only search files changed in the working tree or the index"),
+    switch("git-tracked", None, "Only search files tracked by git"),
+    option("git-rev", None, "REV", "Search the files as they were in this commit"),
+    switch("vimgrep", None, "Print every match as path:line:column:text"),
+    switch("null", Some('0'), "Print a NUL byte after file paths"),
+    switch("heading", None, "Print the file path once above its results"),
+    switch("no-heading", None, "Print the file path in front of every result"),
+    option("path-separator", None, "SEP", "Separate path components with SEP"),
+    switch("each-match", None, "Print every match as line:column:text, a line can be printed more than once"),
+    switch("count-matches", None, "Print how many matches every file has instead of the matches"),
+    choice("column-unit", "UNIT", &["bytes", "chars"], "Count columns in bytes or in characters"),
+    choice("sort", "KEY", SORT_KEYS, "Search files one by one, sorted by KEY"),
+    choice("sortr", "KEY", SORT_KEYS, "Search files one by one, sorted by KEY in reverse"),
+    choice("only", "KIND", &["comments", "strings"],
+        "Only keep matches inside comments or string literals of Rust, C-family, Python and shell files"),
+    switch("not-comments", None, "Drop matches inside comments"),
+    switch("not-strings", None, "Drop matches inside string literals"),
+    option("group-by", None, "CAPTURE",
+        "Print how often every value of this regex capture group was matched, by name or number"),
+    option("top", None, "N", "With --group-by, only print the N most common values"),
+    option("since", None, "TIME", "Only search log lines from this time on, like 2026-10-01T10:00"),
+    option("until", None, "TIME", "Only search log lines from before this time"),
+    option("time-format", None, "FORMAT",
+        "Read the time at the start of a line with this chrono format, like %d.%m.%Y %H:%M:%S"),
+    option("max-depth", None, "N", "Only walk N directories down, 1 only searches the files in the given directories"),
+    option("max-filesize", None, "SIZE", "Skip files larger than SIZE, like 512K, 10M or 1G"),
+    switch("follow", Some('L'), "Follow symbolic links, links back into a directory being walked are reported and skipped"),
+    switch("one-file-system", None, "Do not walk into directories on other file systems"),
+    option("timeout", None, "DURATION", "Stop taking new files after DURATION, like 500ms, 30s or 5m, a file being read is finished first, so a read that hangs is not stopped"),
+    option("glob", None, "GLOB", "Only search files in directories that match this glob, can be given more than once"),
+    option("max-columns", Some('M'), "N", "Print a summary instead of lines longer than N bytes"),
+    switch("max-columns-preview", None, "With --max-columns, print the text around every match of a long line instead"),
+    switch("passthru", None, "Print every line and only highlight the matches, to use minigrep as a filter in a pipeline"),
+    choice("color", "WHEN", &["auto", "always", "never"], "Highlight matches, auto does when printing to a terminal"),
+    switch("json", None, "Print every result as a JSON object on its own line"),
+    option("baseline", None, "FILE",
+        "Compare the matches with the --json output of an earlier run, print what is new, removed or moved and fail on new ones"),
+    option("root", None, "DIR", "With minigrep serve, the directory searches run in"),
+    option("port", None, "PORT", "With minigrep serve, the port to listen on, 0 picks a free one"),
+];
+
+const ARGUMENTS: &str = "[OPTIONS] <QUERY> <PATH>...";
+/* The subcommands, each on its own usage line below the search. */
+const SUBCOMMANDS: [&str; 4] = [
+    "serve [--root <DIR>] --port <PORT>",
+    "save <NAME> -- [OPTIONS] <QUERY> <PATH>...",
+    "run <NAME> [ARGS]...",
+    "history",
+];
+const ABOUT: &str = "Searches files for lines that contain a query.";
+
+/* Finds the flag behind `--long` or `-s`. */
+pub fn find(arg: &str) -> Option<&'static Flag> {
+    if let Some(long) = arg.strip_prefix("--") {
+        return FLAGS.iter().find(|flag| flag.long == long);
+    }
+    let mut chars = arg.strip_prefix('-')?.chars();
+    match (chars.next(), chars.next()) {
+        (Some(short), None) => FLAGS.iter().find(|flag| flag.short == Some(short)),
+        _ => None,
+    }
+}
+
+/* What --generate and --help print instead of searching. */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Generate {
+    Help,
+    Bash,
+    Zsh,
+    Fish,
+    Man,
+}
+
+impl Generate {
+    pub fn parse(kind: &str) -> Result<Generate, &'static str> {
+        match kind {
+            "completions-bash" => Ok(Generate::Bash),
+            "completions-zsh" => Ok(Generate::Zsh),
+            "completions-fish" => Ok(Generate::Fish),
+            "man" => Ok(Generate::Man),
+            _ => Err("Unknown --generate kind, expected completions-bash, completions-zsh, completions-fish or man"),
+        }
+    }
+
+    pub fn render(self) -> String {
+        match self {
+            Generate::Help => help(),
+            Generate::Bash => bash(),
+            Generate::Zsh => zsh(),
+            Generate::Fish => fish(),
+            Generate::Man => man(),
+        }
+    }
+}
+
+fn usage_name(flag: &Flag) -> String {
+    let mut name = match flag.short {
+        Some(short) => format!("-{short}, --{}", flag.long),
+        None => format!("    --{}", flag.long),
+    };
+    if let Some(value) = flag.value {
+        name.push_str(&format!(" <{value}>"));
+    }
+    name
+}
+
+fn help() -> String {
+    let names: Vec<String> = FLAGS.iter().map(usage_name).collect();
+    let width = names.iter().map(String::len).max().unwrap_or(0);
+
+    let mut text = format!("{ABOUT}\n\nUsage: minigrep {ARGUMENTS}\n");
+    for subcommand in SUBCOMMANDS {
+        text.push_str(&format!("       minigrep {subcommand}\n"));
+    }
+    text.push_str("\nOptions:\n");
+    for (flag, name) in FLAGS.iter().zip(&names) {
+        text.push_str(&format!("  {name:width$}  {}", flag.help));
+        if !flag.choices.is_empty() {
+            text.push_str(&format!(" [{}]", flag.choices.join(", ")));
+        }
+        text.push('\n');
+    }
+    text.push_str("\nEnvironment:\n");
+    text.push_str("  IGNORE_CASE       When set, the query matches regardless of case\n");
+    text.push_str("  MINIGREP_HISTORY  The file searches are remembered in, ~/.minigrep_history by default, empty turns it off\n");
+    text
+}
+
+fn bash() -> String {
+    let mut words = Vec::new();
+    let mut cases = String::new();
+    for flag in FLAGS {
+        words.push(format!("--{}", flag.long));
+        let mut patterns = vec![format!("--{}", flag.long)];
+        if let Some(short) = flag.short {
+            words.push(format!("-{short}"));
+            patterns.push(format!("-{short}"));
+        }
+        if flag.value.is_some() {
+            let reply = if flag.choices.is_empty() {
+                String::from("COMPREPLY=()")
+            } else {
+                format!("COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))", flag.choices.join(" "))
+            };
+            cases.push_str(&format!("        {})\n            {reply}\n            return\n            ;;\n", patterns.join("|")));
+        }
+    }
+
+    format!(
+        "_minigrep() {{
+    local cur prev
+    cur=\"${{COMP_WORDS[COMP_CWORD]}}\"
+    prev=\"${{COMP_WORDS[COMP_CWORD-1]}}\"
+
+    case \"$prev\" in
+{cases}    esac
+
+    if [[ \"$cur\" == -* ]]; then
+        COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))
+    else
+        COMPREPLY=($(compgen -f -- \"$cur\"))
+    fi
+}}
+
+complete -o filenames -F _minigrep minigrep
+",
+        words.join(" ")
+    )
+}
+
+fn zsh() -> String {
+    /* Brackets and colons mean something inside an _arguments spec. */
+    let escape = |text: &str| text.replace('[', "\\[").replace(']', "\\]").replace(':', "\\:");
+
+    let mut specs = Vec::new();
+    for flag in FLAGS {
+        let help = escape(flag.help);
+        let value = match flag.value {
+            Some(value) if flag.choices.is_empty() => format!(":{}:", value.to_lowercase()),
+            Some(value) => format!(":{}:({})", value.to_lowercase(), flag.choices.join(" ")),
+            None => String::new(),
+        };
+        specs.push(match flag.short {
+            Some(short) => format!("'(-{short} --{long})'{{-{short},--{long}}}'[{help}]{value}'", long = flag.long),
+            None => format!("'--{}[{help}]{value}'", flag.long),
+        });
+    }
+    specs.push(String::from("':query:'"));
+    specs.push(String::from("'*:path:_files'"));
+
+    format!("#compdef minigrep\n\n_arguments -s \\\n    {}\n", specs.join(" \\\n    "))
+}
+
+fn fish() -> String {
+    let mut text = String::new();
+    for flag in FLAGS {
+        let mut line = format!("complete -c minigrep -l {}", flag.long);
+        if let Some(short) = flag.short {
+            line.push_str(&format!(" -s {short}"));
+        }
+        if flag.value.is_some() {
+            line.push_str(" -r");
+        }
+        if !flag.choices.is_empty() {
+            line.push_str(&format!(" -f -a '{}'", flag.choices.join(" ")));
+        }
+        line.push_str(&format!(" -d '{}'", flag.help.replace('\'', "\\'")));
+        text.push_str(&line);
+        text.push('\n');
+    }
+    text
+}
+
+fn man() -> String {
+    /* roff reads a backslash as an escape and a plain dash as a hyphen. */
+    let escape = |text: &str| text.replace('\\', "\\\\").replace('-', "\\-");
+
+    let mut text = String::from(".TH MINIGREP 1\n.SH NAME\nminigrep \\- search files for lines that contain a query\n");
+    text.push_str(&format!(".SH SYNOPSIS\n.B minigrep\n{}\n", escape(ARGUMENTS)));
+    for subcommand in SUBCOMMANDS {
+        text.push_str(&format!(".br\n.B minigrep\n{}\n", escape(subcommand)));
+    }
+    text.push_str(&format!(".SH DESCRIPTION\n{ABOUT}\n.SH OPTIONS\n"));
+    for flag in FLAGS {
+        text.push_str(&format!(".TP\n.B \"{}\"\n{}", escape(usage_name(flag).trim()), escape(flag.help)));
+        if !flag.choices.is_empty() {
+            text.push_str(&format!(". One of: {}", escape(&flag.choices.join(", "))));
+        }
+        text.push_str(".\n");
+    }
+    text.push_str(".SH ENVIRONMENT\n.TP\n.B IGNORE_CASE\nWhen set, the query matches regardless of case.\n");
+    text.push_str(".TP\n.B MINIGREP_HISTORY\nThe file searches are remembered in, ~/.minigrep_history by default, empty turns it off.\n");
+    text.push_str(".SH EXIT STATUS\n0 when the search ran, 1 when the arguments or the search failed.\n");
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_long_and_short_flags() {
+        assert_eq!("encoding", find("-E").unwrap().long);
+        assert_eq!("encoding", find("--encoding").unwrap().long);
+        assert!(find("--colour").is_none());
+        assert!(find("-Eb").is_none());
+    }
+
+    #[test]
+    fn every_flag_is_in_every_output() {
+        let (help, bash, zsh, fish, man) = (help(), bash(), zsh(), fish(), man());
+        for flag in FLAGS {
+            let long = format!("--{}", flag.long);
+            assert!(help.contains(&long), "{long} is missing from the help");
+            assert!(bash.contains(&long), "{long} is missing from the bash completions");
+            assert!(zsh.contains(&long), "{long} is missing from the zsh completions");
+            assert!(fish.contains(&format!("-l {} ", flag.long)), "{long} is missing from the fish completions");
+            assert!(man.contains(&long.replace('-', "\\-")), "{long} is missing from the man page");
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use std::borrow::Cow;
+use std::error::Error;
+
+use encoding_rs::{Encoding, UTF_8};
+
+/* Text that was decoded from the raw bytes of a file, together with enough information to
+find out where a piece of the text came from in the original file. */
+pub struct Decoded<'a> {
+    pub text: Cow<'a, str>,
+    /* Pairs of (offset in text, offset in the original bytes). A pair is only stored where
+    the distance between the two changes, in between one byte of text is one original byte. */
+    checkpoints: Vec<(usize, usize)>,
+}
+
+impl Decoded<'_> {
+    pub fn original_offset(&self, offset: usize) -> usize {
+        let index = self.checkpoints.partition_point(|&(decoded, _)| decoded <= offset);
+        match index {
+            0 => offset,
+            _ => {
+                let (decoded, original) = self.checkpoints[index - 1];
+                original + (offset - decoded)
+            }
+        }
+    }
+}
+
+/* Turns a label like utf-16le, latin1 or windows-1252 into an encoding. These are the WHATWG
+labels, so latin1 means windows-1252, just like in a browser. */
+pub fn parse_label(label: &str) -> Result<&'static Encoding, &'static str> {
+    Encoding::for_label(label.as_bytes()).ok_or("Unknown encoding label")
+}
+
+/* Whether input that starts with these bytes can be decoded a line at a time. UTF-8 can, but
+after a UTF-16 byte order mark every \n is followed by a zero byte that belongs to it. */
+pub fn splits_into_lines(start: &[u8]) -> bool {
+    Encoding::for_bom(start).is_none_or(|(encoding, _)| encoding == UTF_8)
+}
+
+/* A byte order mark always wins when it agrees with the label (or when there is no label).
+Without either, the file has to be valid UTF-8, which is what read_to_string used to demand. */
+pub fn decode<'a>(
+    bytes: &'a [u8],
+    label: Option<&'static Encoding>,
+) -> Result<Decoded<'a>, Box<dyn Error>> {
+    let (encoding, bom_length) = match (Encoding::for_bom(bytes), label) {
+        (Some((sniffed, length)), None) => (sniffed, length),
+        (Some((sniffed, length)), Some(label)) if sniffed == label => (sniffed, length),
+        (_, Some(label)) => (label, 0),
+        (None, None) => {
+            let text = std::str::from_utf8(bytes)?;
+            return Ok(Decoded { text: Cow::Borrowed(text), checkpoints: Vec::new() });
+        }
+    };
+    let bytes = &bytes[bom_length..];
+
+    /* Valid UTF-8 does not have to be copied, only shifted past its BOM. */
+    if encoding == UTF_8 {
+        if let Ok(text) = std::str::from_utf8(bytes) {
+            return Ok(Decoded { text: Cow::Borrowed(text), checkpoints: vec![(0, bom_length)] });
+        }
+    }
+
+    /* Feed the decoder one byte at a time, so we know which input bytes made up every
+    character it writes out. */
+    let mut decoder = encoding.new_decoder_without_bom_handling();
+    let mut text = String::new();
+    let mut checkpoints: Vec<(usize, usize)> = Vec::new();
+    let mut char_start = 0;
+
+    for index in 0..=bytes.len() {
+        let input = bytes.get(index..index + 1).unwrap_or(&[]);
+        let last = index == bytes.len();
+        let before = text.len();
+
+        /* With this much space reserved the decoder always takes the whole input, so its
+        result does not have to be checked. */
+        text.reserve(decoder.max_utf8_buffer_length(input.len()).unwrap_or(16));
+        let _ = decoder.decode_to_string(input, &mut text, last);
+
+        if text.len() > before {
+            let original = bom_length + char_start;
+            let expected = checkpoints
+                .last()
+                .map(|&(decoded, original)| original + (before - decoded))
+                .unwrap_or(before);
+            if expected != original {
+                checkpoints.push((before, original));
+            }
+            char_start = index + 1;
+        }
+    }
+
+    Ok(Decoded { text: Cow::Owned(text), checkpoints })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn utf16_with_bom() {
+        /* BOM, then "hé\nyo" as UTF-16LE. */
+        let bytes = [0xFF, 0xFE, b'h', 0, 0xE9, 0, b'\n', 0, b'y', 0, b'o', 0];
+        let decoded = decode(&bytes, None).unwrap();
+
+        assert_eq!("hé\nyo", decoded.text);
+        let line_start = decoded.text.find("yo").unwrap();
+        assert_eq!(8, decoded.original_offset(line_start));
+    }
+
+    #[test]
+    fn latin1_by_label() {
+        let bytes = b"caf\xe9 ok\nna\xefve";
+        let decoded = decode(bytes, Some(parse_label("latin1").unwrap())).unwrap();
+
+        assert_eq!("café ok\nnaïve", decoded.text);
+        assert_eq!(8, decoded.original_offset(decoded.text.find("na").unwrap()));
+    }
+
+    #[test]
+    fn utf8_stays_strict_without_a_label() {
+        assert!(decode(b"caf\xe9", None).is_err());
+        assert_eq!(3, decode(b"\xEF\xBB\xBFabc", None).unwrap().original_offset(0));
+    }
+
+    #[test]
+    fn only_utf8_splits_into_lines() {
+        assert!(splits_into_lines(b"plain\n"));
+        assert!(splits_into_lines(b"\xEF\xBB\xBFwith a bom\n"));
+        assert!(!splits_into_lines(&[0xFF, 0xFE, b'h', 0, b'\n']));
+    }
+}
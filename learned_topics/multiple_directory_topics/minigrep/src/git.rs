@@ -0,0 +1,181 @@
+use std::env;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+use git2::{ObjectType, Repository, Status, StatusOptions, TreeWalkMode, TreeWalkResult};
+
+use crate::input::Source;
+
+/* Which files of the git repository around the current directory should be searched. */
+#[derive(Debug, Clone, PartialEq)]
+pub enum GitScope {
+    /* Files that differ from HEAD in the index or in the working tree. */
+    Changed,
+    /* Every file in the index, untracked files are skipped. */
+    Tracked,
+    /* The files as they were in a commit, read from the object database. */
+    Rev(String),
+}
+
+/* Lists the files of a scope that are inside one of the given paths, sorted by path. The
+paths are relative to the current directory, just like the paths that are printed. Files
+from the working tree are read like any other file, files from a commit come with the
+contents of their blob. */
+pub fn files(scope: &GitScope, paths: &[String]) -> Result<Vec<Source>, Box<dyn Error>> {
+    files_in(&env::current_dir()?.canonicalize()?, scope, paths)
+}
+
+fn files_in(cwd: &Path, scope: &GitScope, paths: &[String]) -> Result<Vec<Source>, Box<dyn Error>> {
+    let repo = Repository::discover(cwd).map_err(|_| "not inside a git repository")?;
+    let workdir = repo
+        .workdir()
+        .ok_or("bare repositories cannot be searched")?
+        .canonicalize()?;
+
+    let mut roots = Vec::new();
+    for path in paths {
+        roots.push(cwd.join(path).canonicalize()?);
+    }
+
+    let mut files = Vec::new();
+    match scope {
+        GitScope::Tracked => {
+            for entry in repo.index()?.iter() {
+                let path = String::from_utf8_lossy(&entry.path).into_owned();
+                /* Files deleted from the working tree are still in the index until the
+                deletion is staged, there is nothing left to read for those. */
+                if workdir.join(&path).is_file() {
+                    files.push((path, None));
+                }
+            }
+        }
+        GitScope::Changed => {
+            let changed = Status::INDEX_NEW
+                | Status::INDEX_MODIFIED
+                | Status::INDEX_RENAMED
+                | Status::INDEX_TYPECHANGE
+                | Status::WT_MODIFIED
+                | Status::WT_RENAMED
+                | Status::WT_TYPECHANGE;
+            let mut options = StatusOptions::new();
+            options.include_untracked(false).renames_head_to_index(true);
+
+            for entry in repo.statuses(Some(&mut options))?.iter() {
+                let path = String::from_utf8_lossy(entry.path_bytes()).into_owned();
+                if entry.status().intersects(changed) && workdir.join(&path).is_file() {
+                    files.push((path, None));
+                }
+            }
+        }
+        GitScope::Rev(rev) => {
+            let tree = repo.revparse_single(rev)?.peel_to_tree()?;
+            let mut blobs = Vec::new();
+            /* Only blobs inside the roots are read, and only directories that are inside a root
+            or have one inside them are walked, the rest of the tree is never loaded. */
+            tree.walk(TreeWalkMode::PreOrder, |dir, entry| {
+                let path = format!("{dir}{}", String::from_utf8_lossy(entry.name_bytes()));
+                let absolute = workdir.join(&path);
+                if entry.kind() == Some(ObjectType::Tree) {
+                    return match roots.iter().any(|root| absolute.starts_with(root) || root.starts_with(&absolute)) {
+                        true => TreeWalkResult::Ok,
+                        false => TreeWalkResult::Skip,
+                    };
+                }
+                if !roots.iter().any(|root| absolute.starts_with(root)) {
+                    return TreeWalkResult::Ok;
+                }
+                if let Ok(blob) = entry.to_object(&repo).and_then(|object| object.peel_to_blob()) {
+                    blobs.push((path, blob.content().to_vec()));
+                }
+                TreeWalkResult::Ok
+            })?;
+            files.extend(blobs.into_iter().map(|(path, bytes)| (path, Some(bytes))));
+        }
+    }
+
+    let mut selected = Vec::new();
+    for (path, blob) in files {
+        let absolute = workdir.join(&path);
+        if !roots.iter().any(|root| absolute.starts_with(root)) {
+            continue;
+        }
+        let shown = display_path(&absolute, cwd);
+        let path = match scope {
+            GitScope::Rev(rev) => format!("{rev}:{shown}"),
+            _ => shown,
+        };
+        selected.push(Source { path, blob, named: false });
+    }
+    selected.sort_by(|a, b| a.path.cmp(&b.path));
+
+    Ok(selected)
+}
+
+/* Paths below the current directory are shown relative to it, anything else in full. */
+fn display_path(absolute: &Path, cwd: &Path) -> String {
+    absolute
+        .strip_prefix(cwd)
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| absolute.to_path_buf())
+        .display()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn display_paths_are_relative_to_the_current_directory() {
+        assert_eq!("src/lib.rs", display_path(Path::new("/repo/src/lib.rs"), Path::new("/repo")));
+        assert_eq!("/other/a.txt", display_path(Path::new("/other/a.txt"), Path::new("/repo")));
+    }
+
+    fn paths(files: &[Source]) -> Vec<&str> {
+        files.iter().map(|file| file.path.as_str()).collect()
+    }
+
+    #[test]
+    fn scopes_pick_the_right_files() {
+        let dir = env::temp_dir().join(format!("minigrep-git-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let dir = dir.canonicalize().unwrap();
+        let repo = Repository::init(&dir).unwrap();
+
+        fs::write(dir.join("a.txt"), "first version\n").unwrap();
+        fs::write(dir.join("b.txt"), "never changes\n").unwrap();
+        fs::create_dir(dir.join("sub")).unwrap();
+        fs::write(dir.join("sub/c.txt"), "further down\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("a.txt")).unwrap();
+        index.add_path(Path::new("b.txt")).unwrap();
+        index.add_path(Path::new("sub/c.txt")).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = git2::Signature::now("minigrep", "minigrep@example.com").unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, "first", &tree, &[]).unwrap();
+
+        fs::write(dir.join("a.txt"), "second version\n").unwrap();
+        fs::write(dir.join("untracked.txt"), "not in git\n").unwrap();
+        let all = [".".to_string()];
+
+        let changed = files_in(&dir, &GitScope::Changed, &all).unwrap();
+        assert_eq!(vec!["a.txt"], paths(&changed));
+
+        let tracked = files_in(&dir, &GitScope::Tracked, &all).unwrap();
+        assert_eq!(vec!["a.txt", "b.txt", "sub/c.txt"], paths(&tracked));
+
+        let committed = files_in(&dir, &GitScope::Rev("HEAD".to_string()), &all).unwrap();
+        assert_eq!(vec!["HEAD:a.txt", "HEAD:b.txt", "HEAD:sub/c.txt"], paths(&committed));
+        assert_eq!(Some(b"first version\n".to_vec()), committed[0].blob);
+
+        let below = files_in(&dir, &GitScope::Rev("HEAD".to_string()), &["sub".to_string()]).unwrap();
+        assert_eq!(vec!["HEAD:sub/c.txt"], paths(&below));
+        let one = files_in(&dir, &GitScope::Rev("HEAD".to_string()), &["b.txt".to_string()]).unwrap();
+        assert_eq!(vec!["HEAD:b.txt"], paths(&one));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
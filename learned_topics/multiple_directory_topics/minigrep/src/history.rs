@@ -0,0 +1,182 @@
+use std::env;
+use std::error::Error;
+use std::fs::{File, OpenOptions};
+use std::io::{ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde_json::{json, Value};
+
+use crate::stats::Stats;
+
+/* How large the history file may grow. Once a search makes it larger, the oldest searches are
+dropped until it is half that size, so it is only rewritten once in a while. */
+const MAX_BYTES: u64 = 256 * 1024;
+
+/* One search in the history file. The file has one JSON object for every search, like
+{"time":"2026-10-19T08:00:00Z","args":["--regex","disk","logs"],"matches":3,"files_matched":1} */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub time: String,
+    pub args: Vec<String>,
+    pub matches: usize,
+    pub files_matched: usize,
+}
+
+/* MINIGREP_HISTORY names the history file, ~/.minigrep_history is used without it. Set to
+nothing, it turns the history off. */
+pub fn path() -> Option<PathBuf> {
+    match env::var_os("MINIGREP_HISTORY") {
+        Some(path) if path.is_empty() => None,
+        Some(path) => Some(PathBuf::from(path)),
+        None => env::var_os("HOME").map(|home| Path::new(&home).join(".minigrep_history")),
+    }
+}
+
+/* Adds a search that ran, with the arguments it was given after the program name. */
+pub fn record(path: &Path, args: &[String], stats: &Stats) -> Result<(), Box<dyn Error>> {
+    let entry = Entry {
+        time: DateTime::<Utc>::from(SystemTime::now()).to_rfc3339_opts(SecondsFormat::Secs, true),
+        args: args.to_vec(),
+        matches: stats.matches,
+        files_matched: stats.files_matched,
+    };
+    append(path, &entry)
+}
+
+/* Every search adds one line to the end of the file. Searches running at the same time take
+turns on a lock of the file, so none of them writes while another one trims it. */
+fn append(path: &Path, entry: &Entry) -> Result<(), Box<dyn Error>> {
+    let row = json!({
+        "time": entry.time,
+        "args": entry.args,
+        "matches": entry.matches,
+        "files_matched": entry.files_matched,
+    });
+    let mut file = OpenOptions::new()
+        .create(true)
+        .read(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| format!("{}: {e}", path.display()))?;
+    file.lock()?;
+    file.write_all(format!("{row}\n").as_bytes())?;
+
+    if file.metadata()?.len() > MAX_BYTES {
+        trim(&mut file)?;
+    }
+    Ok(())
+}
+
+/* Keeps the newest lines that fit in half of MAX_BYTES. The file is rewritten in place, not
+replaced, so a search waiting for the lock still appends to the history that is read. */
+fn trim(file: &mut File) -> Result<(), Box<dyn Error>> {
+    let mut bytes = Vec::new();
+    file.seek(SeekFrom::Start(0))?;
+    file.read_to_end(&mut bytes)?;
+
+    let cut = bytes.len().saturating_sub(MAX_BYTES as usize / 2);
+    let start = match bytes[cut..].iter().position(|&byte| byte == b'\n') {
+        Some(newline) if cut > 0 => cut + newline + 1,
+        _ => cut,
+    };
+    file.set_len(0)?;
+    file.write_all(&bytes[start..])?;
+    Ok(())
+}
+
+/* The searches in the history, oldest first. Without a history file there are none. */
+pub fn read() -> Result<Vec<Entry>, Box<dyn Error>> {
+    let Some(path) = path() else {
+        return Ok(Vec::new());
+    };
+    let mut file = match File::open(&path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("{}: {e}", path.display()).into()),
+    };
+    /* Not while a search is trimming it. */
+    file.lock_shared()?;
+    let mut text = String::new();
+    file.read_to_string(&mut text).map_err(|e| format!("{}: {e}", path.display()))?;
+
+    let mut entries = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let row: Value = serde_json::from_str(line).map_err(|e| format!("{}: line {}: {e}", path.display(), index + 1))?;
+        entries.push(Entry {
+            time: row["time"].as_str().unwrap_or_default().to_string(),
+            args: row["args"]
+                .as_array()
+                .map(|args| args.iter().filter_map(Value::as_str).map(String::from).collect())
+                .unwrap_or_default(),
+            matches: row["matches"].as_u64().unwrap_or_default() as usize,
+            files_matched: row["files_matched"].as_u64().unwrap_or_default() as usize,
+        });
+    }
+    Ok(entries)
+}
+
+/* What `minigrep history` prints: when, how many matches, and the command line to run the
+search again. */
+pub fn format(entries: &[Entry]) -> String {
+    let width = entries.iter().map(|entry| entry.matches.to_string().len()).max().unwrap_or(0);
+    entries
+        .iter()
+        .map(|entry| {
+            let args: Vec<String> = entry.args.iter().map(|arg| quote(arg)).collect();
+            format!("{}  {:>width$} matches  minigrep {}\n", entry.time, entry.matches, args.join(" "))
+        })
+        .collect()
+}
+
+/* Puts an argument in single quotes when the shell would split it up or read it differently. */
+fn quote(arg: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "-_./=:,@%+".contains(c);
+    if !arg.is_empty() && arg.chars().all(plain) {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, process};
+
+    use super::*;
+
+    #[test]
+    fn keeps_the_last_searches_and_prints_them_to_run_again() {
+        let dir = env::temp_dir().join(format!("minigrep-history-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("history.jsonl");
+        /* A long argument, so the file passes MAX_BYTES after a few hundred searches. */
+        for matches in 0..300 {
+            let entry = Entry {
+                time: String::from("2026-10-19T08:00:00Z"),
+                args: vec![String::from("--regex"), "x".repeat(1000), String::from("logs")],
+                matches,
+                files_matched: 1,
+            };
+            append(&path, &entry).unwrap();
+        }
+
+        let text = fs::read_to_string(&path).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        let rows: Vec<Value> = text.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert!((text.len() as u64) < MAX_BYTES);
+        assert!(rows.len() < 300);
+        assert_eq!(299, rows.last().unwrap()["matches"]);
+        assert_eq!("'it'\\''s'", quote("it's"));
+        assert_eq!(
+            "2026-10-19T08:00:00Z  12 matches  minigrep --regex 'disk (full|error)' logs\n",
+            format(&[Entry {
+                time: String::from("2026-10-19T08:00:00Z"),
+                args: vec![String::from("--regex"), String::from("disk (full|error)"), String::from("logs")],
+                matches: 12,
+                files_matched: 1,
+            }])
+        );
+    }
+}
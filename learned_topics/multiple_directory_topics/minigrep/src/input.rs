@@ -0,0 +1,128 @@
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::ops::Deref;
+
+use memmap2::Mmap;
+
+/* Files smaller than this are read into a buffer even when mapping is allowed, setting up
+a memory map costs more than copying a few pages. */
+const MMAP_THRESHOLD: u64 = 1024 * 1024;
+
+/* What the user asked for with --mmap or --no-mmap, Auto when they asked for nothing. */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MmapChoice {
+    Auto,
+    Always,
+    Never,
+}
+
+/* The bytes of a file, either borrowed straight from the page cache through a memory map or
+copied into a buffer. Both deref to a byte slice, so the searching code does not care. */
+pub enum Input {
+    Mapped(Mmap),
+    Buffered(Vec<u8>),
+}
+
+impl Deref for Input {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Input::Mapped(map) => map,
+            Input::Buffered(buffer) => buffer,
+        }
+    }
+}
+
+/* Something to search, with the path that is printed in front of its results.
+- blob: the contents, when they do not come from the file system, like a file from a commit.
+- named: the file was named on the command line, instead of found inside a directory. Found
+  files that are not text are skipped, named ones are an error. */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Source {
+    pub path: String,
+    pub blob: Option<Vec<u8>>,
+    pub named: bool,
+}
+
+impl Source {
+    pub fn named(path: &str) -> Source {
+        Source { path: path.to_string(), blob: None, named: true }
+    }
+
+    pub fn found(path: &str) -> Source {
+        Source { path: path.to_string(), blob: None, named: false }
+    }
+
+    /* The size in bytes, None when the file cannot be read. */
+    pub fn size(&self) -> Option<u64> {
+        match &self.blob {
+            Some(bytes) => Some(bytes.len() as u64),
+            None => fs::metadata(&self.path).ok().map(|metadata| metadata.len()),
+        }
+    }
+}
+
+pub fn open(path: &str, choice: MmapChoice) -> io::Result<Input> {
+    let mut file = File::open(path)?;
+    let metadata = file.metadata()?;
+
+    /* Only regular files can be mapped, pipes and special files like /dev/stdin are always
+    read, and so is anything the map fails on. */
+    let wants_map = match choice {
+        MmapChoice::Always => true,
+        MmapChoice::Never => false,
+        MmapChoice::Auto => metadata.len() >= MMAP_THRESHOLD,
+    };
+    if wants_map && metadata.is_file() && metadata.len() > 0 {
+        /* Mapping is unsafe because another process could change the file while we read
+        it. minigrep only ever reads the bytes, so the worst case is a garbled result. */
+        if let Ok(map) = unsafe { Mmap::map(&file) } {
+            return Ok(Input::Mapped(map));
+        }
+    }
+
+    let mut buffer = Vec::with_capacity(metadata.len() as usize);
+    file.read_to_end(&mut buffer)?;
+    Ok(Input::Buffered(buffer))
+}
+
+/* Whether standard input is a pipe or a redirected file, so a search without paths reads it.
+A terminal or /dev/null, like when a program runs minigrep without input, does not count. */
+#[cfg(unix)]
+pub fn stdin_is_piped() -> bool {
+    use std::os::fd::AsFd;
+    use std::os::unix::fs::FileTypeExt;
+
+    let Ok(stdin) = io::stdin().as_fd().try_clone_to_owned() else {
+        return false;
+    };
+    File::from(stdin).metadata().is_ok_and(|metadata| {
+        let kind = metadata.file_type();
+        kind.is_fifo() || kind.is_file() || kind.is_socket()
+    })
+}
+
+#[cfg(not(unix))]
+pub fn stdin_is_piped() -> bool {
+    use std::io::IsTerminal;
+    !io::stdin().is_terminal()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_only_when_allowed() {
+        let path = "poem.txt";
+
+        assert!(matches!(open(path, MmapChoice::Always).unwrap(), Input::Mapped(_)));
+        assert!(matches!(open(path, MmapChoice::Never).unwrap(), Input::Buffered(_)));
+        assert!(matches!(open(path, MmapChoice::Auto).unwrap(), Input::Buffered(_)));
+        assert_eq!(
+            &open(path, MmapChoice::Always).unwrap()[..],
+            &open(path, MmapChoice::Never).unwrap()[..]
+        );
+    }
+}
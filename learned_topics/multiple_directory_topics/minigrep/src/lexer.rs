@@ -0,0 +1,299 @@
+use std::ops::Range;
+use std::path::Path;
+
+/* The parts of source code --only and --not-comments/--not-strings look at. Everything that
+is neither is code. */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Kind {
+    Comment,
+    String,
+}
+
+impl Kind {
+    pub fn parse(name: &str) -> Result<Kind, &'static str> {
+        match name {
+            "comments" => Ok(Kind::Comment),
+            "strings" => Ok(Kind::String),
+            _ => Err("Unknown --only kind, expected comments or strings"),
+        }
+    }
+}
+
+/* Which matches --only and --not-* keep: with only, a match has to start inside a region of
+that kind, and it cannot start inside a region of any kind in not. */
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CodeFilter {
+    pub only: Option<Kind>,
+    pub not: Vec<Kind>,
+}
+
+impl CodeFilter {
+    pub fn keeps(&self, kind: Option<Kind>) -> bool {
+        self.only.is_none_or(|only| kind == Some(only)) && !kind.is_some_and(|kind| self.not.contains(&kind))
+    }
+}
+
+/* The languages the lexer knows. C-family covers every language with C's comments and quotes,
+like Java, JavaScript and Go. */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Language {
+    Rust,
+    CFamily,
+    Python,
+    Shell,
+}
+
+impl Language {
+    /* Guesses the language from the extension, None for anything else. */
+    pub fn from_path(path: &str) -> Option<Language> {
+        let extension = Path::new(path).extension()?.to_str()?;
+        match extension {
+            "rs" => Some(Language::Rust),
+            "c" | "h" | "cc" | "cpp" | "cxx" | "hh" | "hpp" | "java" | "js" | "mjs" | "jsx" | "ts" | "tsx" | "go"
+            | "cs" | "kt" | "swift" => Some(Language::CFamily),
+            "py" | "pyi" => Some(Language::Python),
+            "sh" | "bash" | "zsh" => Some(Language::Shell),
+            _ => None,
+        }
+    }
+}
+
+/* Finds every comment and string literal in the text, in order. This is a lexer of delimiters
+only, it knows where comments and strings start and end and nothing else, which is enough to
+tell them apart from code. Text in a language it does not know has no regions at all. */
+pub fn regions(text: &str, language: Option<Language>) -> Vec<(Range<usize>, Kind)> {
+    let Some(language) = language else {
+        return Vec::new();
+    };
+    let bytes = text.as_bytes();
+    let mut regions = Vec::new();
+    let mut start = 0;
+    while start < bytes.len() {
+        let found = match language {
+            Language::Rust => rust(text, start),
+            Language::CFamily => c_family(bytes, start),
+            Language::Python => python(bytes, start),
+            Language::Shell => shell(bytes, start),
+        };
+        match found {
+            Step::Region(end, kind) => {
+                regions.push((start..end, kind));
+                start = end;
+            }
+            Step::Skip(end) => start = end,
+        }
+    }
+    regions
+}
+
+/* The kind of region the byte at position is in, None for code. */
+pub fn kind_at(regions: &[(Range<usize>, Kind)], position: usize) -> Option<Kind> {
+    let index = regions.partition_point(|(range, _)| range.end <= position);
+    regions
+        .get(index)
+        .filter(|(range, _)| range.contains(&position))
+        .map(|(_, kind)| *kind)
+}
+
+/* What the lexer found at a position: a region ending at the given byte, or code to skip. */
+enum Step {
+    Region(usize, Kind),
+    Skip(usize),
+}
+
+fn rust(text: &str, start: usize) -> Step {
+    let bytes = text.as_bytes();
+    let rest = &bytes[start..];
+    if rest.starts_with(b"//") {
+        return Step::Region(line_end(bytes, start), Kind::Comment);
+    }
+    if rest.starts_with(b"/*") {
+        return Step::Region(block_comment(bytes, start, true), Kind::Comment);
+    }
+    /* r"..." and r#"..."#, the identifier check keeps `for"` style typos from counting. */
+    if rest[0] == b'r' && !(start > 0 && is_identifier(bytes[start - 1])) {
+        let hashes = rest[1..].iter().take_while(|&&byte| byte == b'#').count();
+        if rest.get(1 + hashes) == Some(&b'"') {
+            let closing: Vec<u8> = std::iter::once(b'"').chain(std::iter::repeat_n(b'#', hashes)).collect();
+            let body = start + 2 + hashes;
+            let end = find(bytes, body, &closing).map_or(bytes.len(), |end| end + closing.len());
+            return Step::Region(end, Kind::String);
+        }
+    }
+    match rest[0] {
+        b'"' => Step::Region(quoted(bytes, start, b'"', true, false), Kind::String),
+        /* 'a' and '\n' are literals, 'a on its own is a lifetime. */
+        b'\'' if rest.get(1) == Some(&b'\\') => Step::Region(quoted(bytes, start, b'\'', true, true), Kind::String),
+        b'\'' => {
+            /* The quote is one byte, so the character after it starts right behind it. */
+            let char_len = text[start + 1..].chars().next().map_or(1, char::len_utf8);
+            if rest.get(1 + char_len) == Some(&b'\'') {
+                Step::Region(start + 2 + char_len, Kind::String)
+            } else {
+                Step::Skip(start + 1)
+            }
+        }
+        _ => Step::Skip(start + 1),
+    }
+}
+
+fn c_family(bytes: &[u8], start: usize) -> Step {
+    let rest = &bytes[start..];
+    if rest.starts_with(b"//") {
+        return Step::Region(line_end(bytes, start), Kind::Comment);
+    }
+    if rest.starts_with(b"/*") {
+        return Step::Region(block_comment(bytes, start, false), Kind::Comment);
+    }
+    match rest[0] {
+        /* Character literals count as strings, and so do JavaScript and Go backtick strings. */
+        quote @ (b'"' | b'\'' | b'`') => Step::Region(quoted(bytes, start, quote, true, quote != b'`'), Kind::String),
+        _ => Step::Skip(start + 1),
+    }
+}
+
+fn python(bytes: &[u8], start: usize) -> Step {
+    let rest = &bytes[start..];
+    match rest[0] {
+        b'#' => Step::Region(line_end(bytes, start), Kind::Comment),
+        quote @ (b'"' | b'\'') => {
+            let triple = [quote; 3];
+            if rest.starts_with(&triple) {
+                let end = find_unescaped(bytes, start + 3, &triple).map_or(bytes.len(), |end| end + 3);
+                Step::Region(end, Kind::String)
+            } else {
+                Step::Region(quoted(bytes, start, quote, true, true), Kind::String)
+            }
+        }
+        _ => Step::Skip(start + 1),
+    }
+}
+
+fn shell(bytes: &[u8], start: usize) -> Step {
+    match bytes[start] {
+        /* A backslash takes the next character as it is, quote or hash alike. */
+        b'\\' => Step::Skip(start + 2),
+        /* A # only starts a comment at the start of a word, $# and a#b are not comments. */
+        b'#' if start == 0 || matches!(bytes[start - 1], b' ' | b'\t' | b'\n' | b';' | b'&' | b'|' | b'(') => {
+            Step::Region(line_end(bytes, start), Kind::Comment)
+        }
+        b'\'' => Step::Region(quoted(bytes, start, b'\'', false, false), Kind::String),
+        b'"' => Step::Region(quoted(bytes, start, b'"', true, false), Kind::String),
+        _ => Step::Skip(start + 1),
+    }
+}
+
+fn is_identifier(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_'
+}
+
+fn line_end(bytes: &[u8], start: usize) -> usize {
+    bytes[start..].iter().position(|&byte| byte == b'\n').map_or(bytes.len(), |end| start + end)
+}
+
+fn find(bytes: &[u8], start: usize, needle: &[u8]) -> Option<usize> {
+    bytes.get(start..)?.windows(needle.len()).position(|window| window == needle).map(|end| start + end)
+}
+
+/* Like find, but a backslash hides the character after it. */
+fn find_unescaped(bytes: &[u8], start: usize, needle: &[u8]) -> Option<usize> {
+    let mut position = start;
+    while position < bytes.len() {
+        if bytes[position] == b'\\' {
+            position += 2;
+        } else if bytes[position..].starts_with(needle) {
+            return Some(position);
+        } else {
+            position += 1;
+        }
+    }
+    None
+}
+
+/* The end of a string that starts with the quote at start, just past the closing quote. An
+unclosed string runs to the end of the text, or of the line when it cannot span lines. */
+fn quoted(bytes: &[u8], start: usize, quote: u8, escapes: bool, single_line: bool) -> usize {
+    let mut position = start + 1;
+    while position < bytes.len() {
+        match bytes[position] {
+            b'\\' if escapes => position += 2,
+            byte if byte == quote => return position + 1,
+            b'\n' if single_line => return position,
+            _ => position += 1,
+        }
+    }
+    bytes.len()
+}
+
+/* Rust block comments nest, C ones end at the first closing star and slash. */
+fn block_comment(bytes: &[u8], start: usize, nested: bool) -> usize {
+    let mut depth = 0;
+    let mut position = start;
+    while position < bytes.len() {
+        let rest = &bytes[position..];
+        if rest.starts_with(b"/*") && (nested || depth == 0) {
+            depth += 1;
+            position += 2;
+        } else if rest.starts_with(b"*/") {
+            depth -= 1;
+            position += 2;
+            if depth == 0 {
+                return position;
+            }
+        } else {
+            position += 1;
+        }
+    }
+    bytes.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn found(text: &str, language: Language) -> Vec<(&str, Kind)> {
+        regions(text, Some(language))
+            .into_iter()
+            .map(|(range, kind)| (&text[range], kind))
+            .collect()
+    }
+
+    #[test]
+    fn rust_comments_strings_and_lifetimes() {
+        let code = "fn f<'a>(x: &'a str) -> char { /* a /* nested */ one */ let s = r#\"say \"hi\"\"#; '\"' } // end";
+        assert_eq!(
+            vec![
+                ("/* a /* nested */ one */", Kind::Comment),
+                ("r#\"say \"hi\"\"#", Kind::String),
+                ("'\"'", Kind::String),
+                ("// end", Kind::Comment),
+            ],
+            found(code, Language::Rust)
+        );
+    }
+
+    #[test]
+    fn python_and_shell_hashes() {
+        assert_eq!(
+            vec![("\"\"\"doc # not a comment\"\"\"", Kind::String), ("# real", Kind::Comment)],
+            found("x = \"\"\"doc # not a comment\"\"\" # real", Language::Python)
+        );
+        assert_eq!(
+            vec![("'it'", Kind::String), ("# real", Kind::Comment)],
+            found("echo $# a#b \\# 'it' # real", Language::Shell)
+        );
+    }
+
+    #[test]
+    fn filters_by_kind() {
+        let regions = regions("a = \"url\" // todo", Some(Language::CFamily));
+        let only_comments = CodeFilter { only: Some(Kind::Comment), not: Vec::new() };
+        let no_strings = CodeFilter { only: None, not: vec![Kind::String] };
+
+        assert_eq!(Some(Kind::String), kind_at(&regions, 5));
+        assert!(!only_comments.keeps(kind_at(&regions, 5)));
+        assert!(only_comments.keeps(kind_at(&regions, 12)));
+        assert!(no_strings.keeps(kind_at(&regions, 0)));
+        assert!(!no_strings.keeps(kind_at(&regions, 5)));
+    }
+}
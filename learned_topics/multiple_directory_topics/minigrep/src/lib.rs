@@ -1,41 +1,684 @@
-use std::{fs, env};
+use std::env;
 use std::error::Error;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub mod aggregate;
+pub mod archive;
+pub mod baseline;
+pub mod cli;
+pub mod encoding;
+pub mod git;
+pub mod history;
+pub mod input;
+pub mod lexer;
+pub mod matcher;
+pub mod preprocess;
+pub mod printer;
+pub mod query;
+pub mod saved;
+mod searcher;
+pub mod server;
+pub mod stats;
+pub mod structured;
+pub mod timestamp;
+pub mod walk;
+
+use chrono::format::{Item, StrftimeItems};
+use cli::Generate;
+use encoding_rs::Encoding;
+use git::GitScope;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use input::{MmapChoice, Source};
+use lexer::{CodeFilter, Kind};
+use matcher::{Engine, Matcher};
+use preprocess::Preprocessor;
+use printer::{ColorChoice, ColumnUnit, OutputOptions, Printer};
+use query::Query;
+use saved::Save;
+use searcher::{Outcome, Searcher};
+use server::Serve;
+use stats::{Stats, StatsOutput};
+use structured::{Condition, FieldPath, Format, StructuredSearch};
+use timestamp::TimeWindow;
+use walk::{SortKey, WalkOptions};
 
 pub struct Config {
     pub query: String,
-    pub file_path: String,
+    pub file_paths: Vec<String>,
     pub ignore_case: bool,
+    /* How the query is read, set by --regex and -P. */
+    pub engine: Engine,
+    /* The parsed query when --boolean is given. */
+    pub boolean: Option<Query<String>>,
+    /* Match paragraphs instead of lines, a paragraph ends at an empty line. */
+    pub block: bool,
+    /* Lines end at \r\n instead of \n, the \r is not matched against. */
+    pub crlf: bool,
+    /* Every line is printed, the ones that match with their matches highlighted. */
+    pub passthru: bool,
+    pub structured: Option<StructuredSearch>,
+    pub encoding: Option<&'static Encoding>,
+    pub byte_offset: bool,
+    pub mmap: MmapChoice,
+    pub stats: StatsOutput,
+    pub search_archives: bool,
+    pub preprocessor: Option<Preprocessor>,
+    pub git_scope: Option<GitScope>,
+    /* Files found in directories are only searched when they match one of these. Files named
+    on the command line are always searched. */
+    pub globs: Option<GlobSet>,
+    /* The capture group --group-by counts the values of, by name or number, and how many of
+    the most common values --top prints. */
+    pub group_by: Option<String>,
+    pub top: Option<usize>,
+    /* Set by --only and --not-comments/--not-strings, matches in the wrong part of the
+    source code are dropped. */
+    pub code_filter: Option<CodeFilter>,
+    /* Set by --since and --until, lines logged outside of it are skipped. */
+    pub time_window: Option<TimeWindow>,
+    pub walk: WalkOptions,
+    /* Files larger than this many bytes are skipped. */
+    pub max_filesize: Option<u64>,
+    /* How long a search may take, files that are not started by then are not searched. */
+    pub timeout: Option<Duration>,
+    pub output: OutputOptions,
+    /* The order to search files in, reversed when the bool is true. Without it files are
+    searched in parallel and come out in whatever order they finish. */
+    pub sort: Option<(SortKey, bool)>,
+    /* The --json output of an earlier run, set by --baseline. The matches are compared with it
+    instead of printed. */
+    pub baseline: Option<String>,
+    /* Set by --help and --generate, run prints it instead of searching. */
+    pub generate: Option<Generate>,
+    /* Set by minigrep serve, run answers searches over HTTP instead of searching once. */
+    pub serve: Option<Serve>,
+    /* Set by minigrep save, run writes the search to .minigrep.toml instead of running it. */
+    pub save: Option<Save>,
+    /* Set by minigrep history, run prints the searches that ran before. */
+    pub history: bool,
+    /* The arguments after the program name, and where run records them once the search worked.
+    None when the history is turned off. */
+    pub command_line: Vec<String>,
+    pub history_file: Option<PathBuf>,
 }
 
 impl Config {
     pub fn build(args: &[String]) -> Result<Config, &'static str> {
-        if args.len() < 3 {
+        /* A search is checked the way it will run before it is saved, so a broken one is not. */
+        if args.get(1).is_some_and(|arg| arg == "save") {
+            let save = Save::parse(&args[2..])?;
+            let mut config = Config::build(&save.command_line())?;
+            if !config.is_search() {
+                return Err("Only searches can be saved");
+            }
+            config.save = Some(save);
+            return Ok(config);
+        }
+
+        let mut positional = Vec::new();
+        let mut format = None;
+        let mut selector = None;
+        let mut conditions = Vec::new();
+        let mut columns = Vec::new();
+        let mut encoding = None;
+        let mut byte_offset = false;
+        let mut mmap = MmapChoice::Auto;
+        let mut stats = StatsOutput::None;
+        let mut search_archives = false;
+        let mut pre = None;
+        let mut pre_globs = Vec::new();
+        let mut git_scopes = Vec::new();
+        let mut output = OutputOptions::default();
+        let mut sort = None;
+
+        let mut generate = None;
+        let mut regex = false;
+        let mut perl = false;
+        let mut backtrack_limit = None;
+        let mut boolean = false;
+        let mut block = false;
+        let mut crlf = false;
+        let mut passthru = false;
+        let mut globs = Vec::new();
+        let mut root = None;
+        let mut port = None;
+        let mut since = None;
+        let mut until = None;
+        let mut time_format = None;
+        let mut group_by = None;
+        let mut code_filter = CodeFilter::default();
+        let mut top = None;
+        let mut walk = WalkOptions::default();
+        let mut max_filesize = None;
+        let mut timeout = None;
+        let mut baseline = None;
+
+        /* `minigrep serve` and `minigrep history` are subcommands, to search for those words use
+        `-- serve`. save and run are taken care of before this. */
+        let command_line = args.iter().skip(1).cloned().collect();
+        let serving = args.get(1).is_some_and(|arg| arg == "serve");
+        let history = args.get(1).is_some_and(|arg| arg == "history");
+        let mut args = args.iter().skip(if serving || history { 2 } else { 1 });
+        while let Some(arg) = args.next() {
+            /* Everything after -- is positional, so a query can start with a dash. */
+            if arg == "--" {
+                positional.extend(args.by_ref().cloned());
+                break;
+            }
+            if !arg.starts_with('-') || arg == "-" {
+                positional.push(arg.clone());
+                continue;
+            }
+
+            /* Only flags from cli::FLAGS are accepted, so the help and completions always
+            know about every flag that works. */
+            let flag = cli::find(arg).ok_or("Unknown flag")?;
+            let value = if flag.value.is_some() { flag_value(&mut args)? } else { "" };
+            match flag.long {
+                "help" => generate = Some(Generate::Help),
+                "generate" => generate = Some(Generate::parse(value)?),
+                "regex" => regex = true,
+                "perl-regexp" => perl = true,
+                "backtrack-limit" => {
+                    backtrack_limit = Some(value.parse().map_err(|_| "--backtrack-limit needs a number")?);
+                }
+                "boolean" => boolean = true,
+                "block" => block = true,
+                "crlf" => crlf = true,
+                "format" => format = Some(Format::parse(value)?),
+                "field" => match value.split_once('=') {
+                    Some((name, wanted)) => conditions.push(Condition::new(name, wanted)?),
+                    None => selector = Some(FieldPath::field(value)?),
+                },
+                "jq-like" => selector = Some(FieldPath::jq_like(value)?),
+                "columns" => {
+                    for name in value.split(',') {
+                        columns.push(if name.starts_with('.') {
+                            FieldPath::jq_like(name)?
+                        } else {
+                            FieldPath::field(name)?
+                        });
+                    }
+                }
+                "encoding" => encoding = Some(encoding::parse_label(value)?),
+                "byte-offset" => byte_offset = true,
+                "mmap" => mmap = MmapChoice::Always,
+                "no-mmap" => mmap = MmapChoice::Never,
+                "stats" => stats = StatsOutput::Human,
+                "stats-json" => stats = StatsOutput::Json,
+                "search-archives" => search_archives = true,
+                "pre" => pre = Some(value.to_string()),
+                "pre-glob" => pre_globs.push(value.to_string()),
+                "git-changed" => git_scopes.push(GitScope::Changed),
+                "git-tracked" => git_scopes.push(GitScope::Tracked),
+                "git-rev" => git_scopes.push(GitScope::Rev(value.to_string())),
+                "vimgrep" => output.vimgrep = true,
+                "null" => output.null = true,
+                "heading" => output.heading = true,
+                "no-heading" => output.heading = false,
+                "path-separator" => output.path_separator = Some(value.to_string()),
+                "each-match" => output.each_match = true,
+                "count-matches" => output.count_matches = true,
+                "column-unit" => output.column_unit = ColumnUnit::parse(value)?,
+                "sort" => sort = Some((SortKey::parse(value)?, false)),
+                "sortr" => sort = Some((SortKey::parse(value)?, true)),
+                "max-depth" => walk.max_depth = Some(value.parse().map_err(|_| "--max-depth needs a number")?),
+                "max-filesize" => max_filesize = Some(parse_size(value)?),
+                "follow" => walk.follow = true,
+                "one-file-system" => walk.one_file_system = true,
+                "timeout" => timeout = Some(parse_duration(value)?),
+                "glob" => globs.push(value.to_string()),
+                "max-columns" => output.max_columns = Some(value.parse().map_err(|_| "--max-columns needs a number")?),
+                "max-columns-preview" => output.max_columns_preview = true,
+                "passthru" => passthru = true,
+                "color" => output.color = ColorChoice::parse(value)?,
+                "json" => output.json = true,
+                "baseline" => baseline = Some(value.to_string()),
+                "only" => code_filter.only = Some(Kind::parse(value)?),
+                "not-comments" => code_filter.not.push(Kind::Comment),
+                "not-strings" => code_filter.not.push(Kind::String),
+                "group-by" => group_by = Some(value.to_string()),
+                "top" => top = Some(value.parse().map_err(|_| "--top needs a number")?),
+                "since" => since = Some(value),
+                "until" => until = Some(value),
+                "time-format" => time_format = Some(parse_time_format(value)?),
+                "root" => root = Some(value.to_string()),
+                "port" => port = Some(value.parse().map_err(|_| "--port needs a number from 0 to 65535")?),
+                long => unreachable!("--{long} is in cli::FLAGS but Config::build does not handle it"),
+            }
+        }
+
+        /* vimgrep is a fixed format, there is no room in it for offsets or whole records. */
+        if output.vimgrep && (byte_offset || format.is_some()) {
+            return Err("--vimgrep cannot be combined with --byte-offset or --format");
+        }
+        if output.vimgrep && output.json {
+            return Err("--vimgrep cannot be combined with --json");
+        }
+
+        /* The server takes everything about a search from the request, not from here. */
+        let serve = match (serving, root, port) {
+            (true, _, _) if !positional.is_empty() => return Err("minigrep serve does not take a query or paths"),
+            (true, root, Some(port)) => Some(Serve { root: root.unwrap_or_else(|| String::from(".")), port }),
+            (true, _, None) => return Err("minigrep serve needs --port"),
+            (false, None, None) => None,
+            (false, _, _) => return Err("--root and --port only work with minigrep serve"),
+        };
+        if history && !positional.is_empty() {
+            return Err("minigrep history does not take a query or paths");
+        }
+
+        /* JSON is read by programs, they get every line whole. */
+        if output.max_columns_preview && output.max_columns.is_none() {
+            return Err("--max-columns-preview needs --max-columns");
+        }
+        if output.max_columns.is_some() && output.json {
+            return Err("--max-columns cannot be combined with --json");
+        }
+
+        /* The baseline is made of matching lines, and is compared with matching lines. */
+        if baseline.is_some()
+            && (output.count_matches || output.vimgrep || format.is_some() || group_by.is_some() || passthru)
+        {
+            return Err("--baseline cannot be combined with --count-matches, --vimgrep, --format, --group-by or --passthru");
+        }
+
+        /* A count has no lines, so there is nothing to print the matches on. */
+        if output.count_matches && (output.vimgrep || output.each_match) {
+            return Err("--count-matches cannot be combined with --vimgrep or --each-match");
+        }
+
+        if git_scopes.len() > 1 {
+            return Err("Only one of --git-changed, --git-tracked and --git-rev can be used");
+        }
+        let git_scope = git_scopes.pop();
+
+        /* A git scope already says which files to search, so paths are optional there and
+        only narrow the search down. */
+        let required = match (&generate, &git_scope) {
+            _ if serve.is_some() || history => 0,
+            (Some(_), _) => 0,
+            (None, Some(_)) => 1,
+            (None, None) => 2,
+        };
+        /* With only a query, what is piped in is searched, like `tail -f app.log | minigrep ERROR`. */
+        if required == 2 && positional.len() == 1 && input::stdin_is_piped() {
+            positional.push(String::from("-"));
+        }
+        if positional.len() < required {
             return Err("Not enough arguments");
         }
-        let query = args[1].clone();
-        let file_path = args[2].clone();
+        let query = positional.first().cloned().unwrap_or_default();
+        let mut file_paths = positional.get(1..).unwrap_or_default().to_vec();
+        if file_paths.is_empty() {
+            file_paths.push(String::from("."));
+        }
 
         let ignore_case = env::var("IGNORE_CASE").is_ok();
+        let engine = match (perl, regex) {
+            (true, _) => Engine::Backtracking { limit: backtrack_limit.unwrap_or(matcher::DEFAULT_BACKTRACK_LIMIT) },
+            _ if backtrack_limit.is_some() => return Err("--backtrack-limit needs -P"),
+            (false, true) => Engine::Regex,
+            (false, false) => Engine::Literal,
+        };
+        let boolean = if boolean { Some(Query::parse(&query)?) } else { None };
+
+        /* Records already are the unit that is matched, there are no paragraphs in them. */
+        if block && format.is_some() {
+            return Err("--block cannot be combined with --format");
+        }
+
+        let structured = match format {
+            Some(format) => Some(StructuredSearch { format, selector, conditions, columns }),
+            None if selector.is_some() || !conditions.is_empty() || !columns.is_empty() => {
+                return Err("--field, --jq-like and --columns need --format csv or jsonl");
+            }
+            None => None,
+        };
+
+        let globs = if globs.is_empty() {
+            None
+        } else {
+            let mut builder = GlobSetBuilder::new();
+            for glob in &globs {
+                builder.add(Glob::new(glob).map_err(|_| "Invalid --glob pattern")?);
+            }
+            Some(builder.build().map_err(|_| "Invalid --glob pattern")?)
+        };
 
-        Ok(Config { query, file_path, ignore_case })
+        /* A histogram counts captures of single lines, so it needs a regex and plain lines. */
+        if group_by.is_some() && (engine == Engine::Literal || boolean.is_some()) {
+            return Err("--group-by needs --regex or -P and cannot be combined with --boolean");
+        }
+        if group_by.is_some() && (block || structured.is_some() || output.count_matches || output.vimgrep) {
+            return Err("--group-by cannot be combined with --block, --format, --count-matches or --vimgrep");
+        }
+        if top.is_some() && group_by.is_none() {
+            return Err("--top needs --group-by");
+        }
+
+        /* Comments and strings can span lines, so they are found in the whole file and then
+        matched up with the lines. Paragraphs and records are not lines. */
+        let code_filter = (code_filter != CodeFilter::default()).then_some(code_filter);
+        if code_filter.is_some() && (block || structured.is_some()) {
+            return Err("--only, --not-comments and --not-strings cannot be combined with --block or --format");
+        }
+
+        let time_window = match (since, until) {
+            (None, None) if time_format.is_some() => return Err("--time-format needs --since or --until"),
+            (None, None) => None,
+            /* Lines are filtered one by one, records and paragraphs have no single time. */
+            _ if block || structured.is_some() => {
+                return Err("--since and --until cannot be combined with --block or --format");
+            }
+            (since, until) => Some(TimeWindow::build(since, until, time_format)?),
+        };
+
+        /* Passthru prints lines as they are, there is no room for records, counts or extra columns. */
+        if passthru
+            && (block
+                || structured.is_some()
+                || group_by.is_some()
+                || output.count_matches
+                || output.each_match
+                || output.vimgrep
+                || output.json)
+        {
+            return Err("--passthru cannot be combined with --block, --format, --group-by, --count-matches, \
+                --each-match, --vimgrep or --json");
+        }
+
+        let preprocessor = match pre {
+            Some(command) => Some(Preprocessor::build(command, &pre_globs)?),
+            None if !pre_globs.is_empty() => return Err("--pre-glob needs --pre"),
+            None => None,
+        };
+
+        Ok(Config {
+            query,
+            file_paths,
+            ignore_case,
+            engine,
+            boolean,
+            block,
+            crlf,
+            passthru,
+            structured,
+            encoding,
+            byte_offset,
+            mmap,
+            stats,
+            search_archives,
+            preprocessor,
+            git_scope,
+            globs,
+            group_by,
+            top,
+            code_filter,
+            time_window,
+            walk,
+            max_filesize,
+            timeout,
+            output,
+            sort,
+            baseline,
+            generate,
+            serve,
+            save: None,
+            history,
+            command_line,
+            history_file: history::path(),
+        })
+    }
+
+    /* Whether run searches files, rather than printing help, serving, saving or listing. */
+    pub fn is_search(&self) -> bool {
+        self.generate.is_none() && self.serve.is_none() && self.save.is_none() && !self.history
     }
 }
 
-pub fn run(config: Config) -> Result<(), Box<dyn Error>>{
-    /* Opens a file and returns a Result<String> of the file's contents. */
-    let contents = fs::read_to_string(config.file_path)?;
+/* Flags like --format take the next argument as their value. */
+fn flag_value<'a>(args: &mut impl Iterator<Item = &'a String>) -> Result<&'a str, &'static str> {
+    args.next().map(String::as_str).ok_or("Missing value for flag")
+}
 
-    let results = if config.ignore_case {
-        search_case_insensitive(&config.query, &contents)
-    } else {
-        search(&config.query, &contents)
+/* --max-filesize takes bytes, or a number with K, M or G behind it, in powers of 1024. */
+fn parse_size(value: &str) -> Result<u64, &'static str> {
+    let (number, unit) = match value.char_indices().find(|(_, c)| !c.is_ascii_digit()) {
+        Some((index, _)) => value.split_at(index),
+        None => (value, ""),
     };
+    let factor = match unit.to_ascii_uppercase().as_str() {
+        "" => 1,
+        "K" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        _ => return Err("--max-filesize takes a size like 512K, 10M or 1G"),
+    };
+    number
+        .parse::<u64>()
+        .ok()
+        .and_then(|number| number.checked_mul(factor))
+        .ok_or("--max-filesize takes a size like 512K, 10M or 1G")
+}
 
-    for line in results {
-        println!("{line}");
+/* --timeout takes seconds, or a number with ms, s, m or h behind it. */
+fn parse_duration(value: &str) -> Result<Duration, &'static str> {
+    let (number, unit) = match value.char_indices().find(|(_, c)| !c.is_ascii_digit()) {
+        Some((index, _)) => value.split_at(index),
+        None => (value, "s"),
+    };
+    let number: u64 = number.parse().map_err(|_| "--timeout takes a duration like 500ms, 30s or 5m")?;
+    match unit {
+        "ms" => Ok(Duration::from_millis(number)),
+        "s" => Ok(Duration::from_secs(number)),
+        "m" => Ok(Duration::from_secs(number.saturating_mul(60))),
+        "h" => Ok(Duration::from_secs(number.saturating_mul(3600))),
+        _ => Err("--timeout takes a duration like 500ms, 30s or 5m"),
     }
+}
 
-    Ok(())
+/* chrono only finds out that a format is broken when it parses with it, and then every line
+looks like a continuation line, so nothing would be searched without a word. */
+fn parse_time_format(value: &str) -> Result<String, &'static str> {
+    match StrftimeItems::new(value).any(|item| item == Item::Error) {
+        true => Err("--time-format is not a valid chrono format"),
+        false => Ok(value.to_string()),
+    }
+}
+
+pub fn run(config: Config) -> Result<Stats, Box<dyn Error>> {
+    if let Some(generate) = config.generate {
+        print!("{}", generate.render());
+        return Ok(Stats::default());
+    }
+    if let Some(serve) = &config.serve {
+        server::serve(serve)?;
+        return Ok(Stats::default());
+    }
+    if let Some(save) = &config.save {
+        save.write(Path::new(saved::FILE))?;
+        println!("Saved {} in {}", save.name, saved::FILE);
+        return Ok(Stats::default());
+    }
+    if config.history {
+        print!("{}", history::format(&history::read()?));
+        return Ok(Stats::default());
+    }
+
+    let started = Instant::now();
+    let mut options = config.output.clone();
+    options.color = options.color.resolve(io::stdout().is_terminal());
+    /* With a baseline the results are collected as JSON, the same way the baseline was made,
+    and only the differences are printed. */
+    let baseline = config.baseline.as_deref().map(baseline::read).transpose()?;
+    let collected = baseline::Collected::default();
+    let printer = match baseline {
+        Some(_) => Printer::new(OutputOptions { json: true, ..options }, Box::new(collected.clone())),
+        None => Printer::new(options, Box::new(io::stdout())),
+    };
+    let Outcome { mut stats, errors, histogram, .. } = search_with(&config, &printer)?;
+
+    if config.group_by.is_some() {
+        print!("{}", histogram.format(config.top, config.output.json));
+    }
+    let mut new_matches = 0;
+    if let Some(baseline) = &baseline {
+        let diff = baseline::compare(baseline, &baseline::parse(&collected.text())?);
+        print!("{}", diff.format(config.output.json));
+        new_matches = diff.count(|change| *change == baseline::Change::New);
+    }
+    stats.elapsed = started.elapsed();
+    match config.stats {
+        StatsOutput::None => {}
+        StatsOutput::Human => println!("\n{stats}"),
+        StatsOutput::Json => println!("{}", stats.to_json()),
+    }
+
+    /* The results of the paths that worked are already out, the rest is reported at the end. */
+    for error in &errors {
+        eprintln!("{error}");
+    }
+    if !errors.is_empty() {
+        return Err(format!("{} error(s) while searching", errors.len()).into());
+    }
+    if new_matches > 0 {
+        return Err(format!("{new_matches} new match(es) that are not in the baseline").into());
+    }
+
+    /* The history is a convenience, a search that worked does not fail because of it. */
+    if let Some(path) = &config.history_file {
+        if let Err(e) = history::record(path, &config.command_line, &stats) {
+            eprintln!("Could not update the search history: {e}");
+        }
+    }
+    Ok(stats)
+}
+
+/* Finds the files, searches them and prints the results through the printer. The server uses
+this too, with a printer that writes to the connection. Paths that cannot be searched end up in
+the errors of the outcome, only a query that does not work is an error of its own. */
+pub(crate) fn search_with(config: &Config, printer: &Printer) -> Result<Outcome, Box<dyn Error>> {
+    let deadline = config.timeout.map(|timeout| Instant::now() + timeout);
+    let mut walked = match &config.git_scope {
+        Some(scope) => walk::Walked { sources: git::files(scope, &config.file_paths)?, ..Default::default() },
+        None => walk::sources(&config.file_paths, &config.walk, deadline),
+    };
+    let sources = &mut walked.sources;
+    if let Some(globs) = &config.globs {
+        sources.retain(|source| source.named || globs.is_match(&source.path));
+    }
+    if let Some(max) = config.max_filesize {
+        sources.retain(|source| source.size().is_none_or(|size| size <= max));
+    }
+
+    /* With more than one file every result starts with the file it was found in. */
+    let show_names = config.git_scope.is_some()
+        || config.file_paths.len() > 1
+        || sources.len() > 1
+        || sources.iter().any(|source| !source.named);
+
+    let matcher: Box<dyn Matcher> = match (&config.boolean, &config.group_by) {
+        (Some(query), _) => Box::new(query.clone().build(config.ignore_case, config.engine)?),
+        (None, Some(group)) => {
+            let regex = matcher::build(&config.query, config.ignore_case, config.engine)?;
+            if !regex.has_group(group) {
+                return Err(format!("the regex has no capture group {group}").into());
+            }
+            regex
+        }
+        (None, None) => matcher::build(&config.query, config.ignore_case, config.engine)?,
+    };
+    let mut outcome = match config.sort {
+        Some((key, reverse)) => {
+            walk::sort(sources, key, reverse);
+            search_in_order(config, printer, matcher.as_ref(), sources, show_names, deadline)
+        }
+        None => search_in_parallel(config, printer, matcher.as_ref(), sources, show_names, deadline),
+    };
+
+    outcome.errors.append(&mut walked.errors);
+    outcome.errors.sort();
+    outcome.timed_out |= walked.timed_out;
+    if outcome.timed_out {
+        outcome.errors.push(String::from("the --timeout passed before every file was searched"));
+    }
+    Ok(outcome)
+}
+
+fn search_in_order(
+    config: &Config,
+    printer: &Printer,
+    matcher: &dyn Matcher,
+    sources: &[Source],
+    show_names: bool,
+    deadline: Option<Instant>,
+) -> Outcome {
+    let mut searcher = Searcher::new(config, printer, matcher);
+    for source in sources {
+        if searcher.timed_out(deadline) {
+            break;
+        }
+        searcher.search_source(source, show_names);
+    }
+    searcher.finish()
+}
+
+/* Every thread takes the next file that nobody is working on yet, until there are none left
+or the deadline has passed. A file that fails is kept in the errors and the thread moves on. */
+fn search_in_parallel(
+    config: &Config,
+    printer: &Printer,
+    matcher: &dyn Matcher,
+    sources: &[Source],
+    show_names: bool,
+    deadline: Option<Instant>,
+) -> Outcome {
+    let threads = thread::available_parallelism()
+        .map(|threads| threads.get())
+        .unwrap_or(1)
+        .min(sources.len());
+    if threads <= 1 {
+        return search_in_order(config, printer, matcher, sources, show_names, deadline);
+    }
+
+    let next = AtomicUsize::new(0);
+    let results: Vec<Outcome> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut searcher = Searcher::new(config, printer, matcher);
+                    while !searcher.timed_out(deadline) {
+                        let Some(source) = sources.get(next.fetch_add(1, Ordering::SeqCst)) else {
+                            break;
+                        };
+                        searcher.search_source(source, show_names);
+                    }
+                    searcher.finish()
+                })
+            })
+            .collect();
+        workers.into_iter().map(|worker| worker.join().unwrap()).collect()
+    });
+
+    let mut outcome = Outcome::default();
+    for part in results {
+        outcome.add(part);
+    }
+    outcome
+}
+
+/* The same check search and search_case_insensitive do, for a single piece of text. */
+pub(crate) fn contains(text: &str, query: &str, ignore_case: bool) -> bool {
+    if ignore_case {
+        text.to_lowercase().contains(&query.to_lowercase())
+    } else {
+        text.contains(query)
+    }
 }
 
 /* Normally you would not want to use clone because the runtime performance will be worse,
@@ -47,7 +690,9 @@ However this function could fail, and programmers expect new to never fail, so t
 changed the name to build.
  */
 
+/* The tests stay above search and search_case_insensitive, where they started out. */
 #[cfg(test)]
+#[allow(clippy::items_after_test_module)]
 mod tests {
     use super::*;
 
@@ -77,6 +722,64 @@ Trust me.";
             search_case_insensitive(query, contents)
         )
     }
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn field_flags_need_a_format() {
+        let config = Config::build(&args(&["minigrep", "--field", "level=error", "", "log.jsonl"]));
+        assert!(config.is_err());
+
+        let config = Config::build(&args(&[
+            "minigrep", "--format", "jsonl", "--field", "level=error", "--jq-like", ".user.id", "7", "log.jsonl",
+        ]))
+        .unwrap();
+        let structured = config.structured.unwrap();
+        assert_eq!(Format::JsonLines, structured.format);
+        assert_eq!(1, structured.conditions.len());
+        assert_eq!(Some(FieldPath::jq_like(".user.id").unwrap()), structured.selector);
+        assert_eq!("7", config.query);
+    }
+
+    #[test]
+    fn run_returns_stats() {
+        let config = Config::build(&args(&["minigrep", "nobody", "poem.txt", "poem.txt"])).unwrap();
+        let stats = run(Config { history_file: None, ..config }).unwrap();
+
+        assert_eq!(2, stats.files_searched);
+        assert_eq!(2, stats.files_matched);
+        assert_eq!(4, stats.matches);
+        assert_eq!(18, stats.lines_scanned);
+    }
+
+    #[test]
+    fn sizes_and_durations() {
+        assert_eq!(Ok(10 << 20), parse_size("10M"));
+        assert_eq!(Ok(512), parse_size("512"));
+        assert!(parse_size("10MB").is_err());
+        assert_eq!(Ok(Duration::from_millis(500)), parse_duration("500ms"));
+        assert_eq!(Ok(Duration::from_secs(30)), parse_duration("30"));
+        assert_eq!(Ok(Duration::from_secs(300)), parse_duration("5m"));
+        assert!(parse_duration("-1s").is_err());
+        assert_eq!(Ok(String::from("%d/%m/%Y %H:%M")), parse_time_format("%d/%m/%Y %H:%M"));
+        assert_eq!(Err("--time-format is not a valid chrono format"), parse_time_format("%Q%"));
+    }
+
+    #[test]
+    fn every_flag_in_the_table_is_handled() {
+        for flag in cli::FLAGS {
+            let mut list = vec!["minigrep".to_string(), format!("--{}", flag.long)];
+            if flag.value.is_some() {
+                list.push(flag.choices.first().unwrap_or(&"x").to_string());
+            }
+            list.extend(args(&["nobody", "poem.txt"]));
+
+            /* Some values are not valid for their flag, what matters is that none panic. */
+            let _ = Config::build(&list);
+        }
+    }
 }
 
 pub fn search<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
@@ -102,4 +805,4 @@ pub fn search_case_insensitive<'a>(query: &str, contents: &'a str) -> Vec<&'a st
     }
 
     results
-}
\ No newline at end of file
+}
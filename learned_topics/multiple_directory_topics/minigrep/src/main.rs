@@ -1,15 +1,19 @@
 use std::{env, process};
 
-use minigrep::Config;
+use minigrep::{saved, Config};
 
 fn main() {
     /* Gets the Arguments given in by the user and put them in a Vector */
     let args: Vec<String> = env::args().collect();
 
-    let config = Config::build(&args).unwrap_or_else(|err| {
-        eprintln!("Problem parsing arguments: {err}");
-        process::exit(1);
-    });
+    /* minigrep run <name> becomes the arguments the search was saved with. */
+    let config = saved::expand(args)
+        .and_then(|args| Ok(Config::build(&args)?))
+        .unwrap_or_else(|err| {
+            eprintln!("Problem parsing arguments: {err}");
+            eprintln!("Run minigrep --help to see what minigrep accepts.");
+            process::exit(1);
+        });
 
     if let Err(e) = minigrep::run(config) {
         eprintln!("Application error: {e}");
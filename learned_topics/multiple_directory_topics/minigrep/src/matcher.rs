@@ -0,0 +1,277 @@
+use std::error::Error;
+use std::ops::Range;
+
+use fancy_regex::RuntimeError;
+use regex::RegexBuilder;
+
+/* Something that can find the query in a line. Every match is a byte range of the line, so
+the printer can point at the column where it starts. One matcher is shared by all threads,
+hence Send and Sync. Searching only fails for an engine that can give up on a line, like the
+backtracking one. */
+pub trait Matcher: Send + Sync {
+    /* All non-overlapping matches in the line, from left to right. */
+    fn find_iter(&self, line: &str) -> Result<Vec<Range<usize>>, Box<dyn Error>>;
+
+    fn is_match(&self, line: &str) -> Result<bool, Box<dyn Error>> {
+        Ok(!self.find_iter(line)?.is_empty())
+    }
+
+    /* Whether the query has this capture group, by name or number. Only a regex has groups. */
+    fn has_group(&self, _group: &str) -> bool {
+        false
+    }
+
+    /* What the capture group matched, for every match in the line. */
+    fn captures(&self, _line: &str, _group: &str) -> Result<Vec<String>, Box<dyn Error>> {
+        Ok(Vec::new())
+    }
+}
+
+/* How the query is read, picked with --regex and -P.
+- Literal: as it is written.
+- Regex: as a regex for the default engine, which takes linear time on every pattern.
+- Backtracking: as a regex with lookaround and backreferences, which takes a backtracking
+  engine. That engine gives up on a line after limit steps, instead of running for as long as
+  a catastrophic pattern like (a+)+\1$ would take. */
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Engine {
+    #[default]
+    Literal,
+    Regex,
+    Backtracking { limit: usize },
+}
+
+/* The limit -P uses without --backtrack-limit, the one the engine itself comes with. */
+pub const DEFAULT_BACKTRACK_LIMIT: usize = 1_000_000;
+
+/* The plain text search minigrep always did, the query has to appear as it is written. */
+pub struct Literal {
+    query: String,
+    ignore_case: bool,
+}
+
+impl Literal {
+    pub fn new(query: &str, ignore_case: bool) -> Literal {
+        let query = if ignore_case {
+            query.to_lowercase()
+        } else {
+            query.to_string()
+        };
+        Literal { query, ignore_case }
+    }
+}
+
+impl Matcher for Literal {
+    fn find_iter(&self, line: &str) -> Result<Vec<Range<usize>>, Box<dyn Error>> {
+        /* An empty query matches every line, but only once, at its start. */
+        if self.query.is_empty() {
+            return Ok(std::iter::once(0..0).collect());
+        }
+        if !self.ignore_case {
+            return Ok(line
+                .match_indices(&self.query)
+                .map(|(start, found)| start..start + found.len())
+                .collect());
+        }
+
+        /* Lowercasing can change how many bytes a character takes, so remember where every
+        lowercased character came from in the original line. */
+        let mut lowered = String::with_capacity(line.len());
+        let mut origins = Vec::with_capacity(line.len());
+        for (start, c) in line.char_indices() {
+            for lower in c.to_lowercase() {
+                origins.extend(std::iter::repeat_n(start, lower.len_utf8()));
+                lowered.push(lower);
+            }
+        }
+        origins.push(line.len());
+
+        Ok(lowered
+            .match_indices(&self.query)
+            .map(|(start, found)| {
+                let end = start + found.len();
+                /* A match that ends halfway into a character still covers all of it. */
+                let end = origins[end..]
+                    .iter()
+                    .copied()
+                    .find(|&origin| origin > origins[end - 1])
+                    .unwrap_or(line.len());
+                origins[start]..end
+            })
+            .collect())
+    }
+
+    fn is_match(&self, line: &str) -> Result<bool, Box<dyn Error>> {
+        if self.ignore_case {
+            Ok(line.to_lowercase().contains(&self.query))
+        } else {
+            Ok(line.contains(&self.query))
+        }
+    }
+}
+
+/* The query as a regular expression, when --regex is given. */
+pub struct Regex {
+    regex: regex::Regex,
+}
+
+impl Regex {
+    pub fn new(pattern: &str, ignore_case: bool) -> Result<Regex, regex::Error> {
+        let regex = RegexBuilder::new(pattern).case_insensitive(ignore_case).build()?;
+        Ok(Regex { regex })
+    }
+
+    /* The index of a capture group, given by its number or its name. */
+    pub fn group(&self, group: &str) -> Option<usize> {
+        match group.parse::<usize>() {
+            Ok(index) => (index < self.regex.captures_len()).then_some(index),
+            Err(_) => self.regex.capture_names().position(|name| name == Some(group)),
+        }
+    }
+}
+
+impl Matcher for Regex {
+    fn find_iter(&self, line: &str) -> Result<Vec<Range<usize>>, Box<dyn Error>> {
+        Ok(self.regex.find_iter(line).map(|found| found.range()).collect())
+    }
+
+    fn is_match(&self, line: &str) -> Result<bool, Box<dyn Error>> {
+        Ok(self.regex.is_match(line))
+    }
+
+    fn has_group(&self, group: &str) -> bool {
+        self.group(group).is_some()
+    }
+
+    fn captures(&self, line: &str, group: &str) -> Result<Vec<String>, Box<dyn Error>> {
+        let Some(index) = self.group(group) else {
+            return Ok(Vec::new());
+        };
+        /* A group that took no part in a match, like an optional one, has nothing to count. */
+        Ok(self
+            .regex
+            .captures_iter(line)
+            .filter_map(|captures| captures.get(index))
+            .map(|found| found.as_str().to_string())
+            .collect())
+    }
+}
+
+/* The query as a regex for the backtracking engine, when -P is given. */
+pub struct Backtracking {
+    regex: fancy_regex::Regex,
+    limit: usize,
+}
+
+impl Backtracking {
+    pub fn new(pattern: &str, ignore_case: bool, limit: usize) -> Result<Backtracking, fancy_regex::Error> {
+        let regex = fancy_regex::RegexBuilder::new(pattern)
+            .case_insensitive(ignore_case)
+            .backtrack_limit(limit)
+            .build()?;
+        Ok(Backtracking { regex, limit })
+    }
+
+    pub fn group(&self, group: &str) -> Option<usize> {
+        match group.parse::<usize>() {
+            Ok(index) => (index < self.regex.captures_len()).then_some(index),
+            Err(_) => self.regex.capture_names().position(|name| name == Some(group)),
+        }
+    }
+
+    /* Running out of steps is worth a hint, the engine's own message does not say how to fix it. */
+    fn explain(&self, error: fancy_regex::Error) -> Box<dyn Error> {
+        match error {
+            fancy_regex::Error::RuntimeError(RuntimeError::BacktrackLimitExceeded) => format!(
+                "the pattern took more than {} backtracking steps, simplify it or raise --backtrack-limit",
+                self.limit
+            )
+            .into(),
+            error => error.into(),
+        }
+    }
+}
+
+impl Matcher for Backtracking {
+    fn find_iter(&self, line: &str) -> Result<Vec<Range<usize>>, Box<dyn Error>> {
+        let mut found = Vec::new();
+        for result in self.regex.find_iter(line) {
+            found.push(result.map_err(|e| self.explain(e))?.range());
+        }
+        Ok(found)
+    }
+
+    fn is_match(&self, line: &str) -> Result<bool, Box<dyn Error>> {
+        self.regex.is_match(line).map_err(|e| self.explain(e))
+    }
+
+    fn has_group(&self, group: &str) -> bool {
+        self.group(group).is_some()
+    }
+
+    fn captures(&self, line: &str, group: &str) -> Result<Vec<String>, Box<dyn Error>> {
+        let Some(index) = self.group(group) else {
+            return Ok(Vec::new());
+        };
+        let mut values = Vec::new();
+        for captures in self.regex.captures_iter(line) {
+            let captures = captures.map_err(|e| self.explain(e))?;
+            values.extend(captures.get(index).map(|found| found.as_str().to_string()));
+        }
+        Ok(values)
+    }
+}
+
+/* Picks the matcher for a search. Only a regex can fail, when it is not a valid pattern. */
+pub fn build(query: &str, ignore_case: bool, engine: Engine) -> Result<Box<dyn Matcher>, Box<dyn Error>> {
+    Ok(match engine {
+        Engine::Literal => Box::new(Literal::new(query, ignore_case)),
+        Engine::Regex => Box::new(Regex::new(query, ignore_case)?),
+        Engine::Backtracking { limit } => Box::new(Backtracking::new(query, ignore_case, limit)?),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_every_occurrence() {
+        let matcher = Literal::new("ab", false);
+        assert_eq!(vec![0..2, 4..6], matcher.find_iter("ab--ab-aB").unwrap());
+    }
+
+    #[test]
+    fn ignore_case_keeps_original_offsets() {
+        let matcher = Literal::new("STRASSE", true);
+        let found = matcher.find_iter("in Strasse").unwrap();
+        assert_eq!((1, 3, 10), (found.len(), found[0].start, found[0].end));
+
+        /* İ lowercases to two characters, the match after it still points at the right bytes. */
+        let matcher = Literal::new("rust", true);
+        let line = "İ love Rust";
+        let found = matcher.find_iter(line).unwrap();
+        assert_eq!(vec!["Rust"], found.iter().map(|range| &line[range.clone()]).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn regex_finds_patterns() {
+        let matcher = build("b[aeiou]g", true, Engine::Regex).unwrap();
+        assert_eq!(vec![3..6, 8..11], matcher.find_iter("no BAG, big").unwrap());
+        assert!(build("(unclosed", false, Engine::Regex).is_err());
+        assert!(build("(?<=id=)\\d+", false, Engine::Regex).is_err());
+    }
+
+    #[test]
+    fn backtracking_supports_lookaround_and_gives_up() {
+        let engine = Engine::Backtracking { limit: DEFAULT_BACKTRACK_LIMIT };
+        let matcher = build("(?<=id=)\\d+", false, engine).unwrap();
+        assert_eq!(vec![3..5], matcher.find_iter("id=42, pid 7").unwrap());
+        let matcher = build("(\\w+) \\1", false, engine).unwrap();
+        assert!(matcher.is_match("it is is twice").unwrap());
+
+        let matcher = build("(a+)+\\1$", false, Engine::Backtracking { limit: 1000 }).unwrap();
+        let error = matcher.find_iter(&format!("{}!", "a".repeat(30))).unwrap_err();
+        assert!(error.to_string().contains("more than 1000 backtracking steps"));
+    }
+}
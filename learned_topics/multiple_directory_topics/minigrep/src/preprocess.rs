@@ -0,0 +1,75 @@
+use std::error::Error;
+use std::fs::File;
+use std::process::{Command, Stdio};
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+
+/* A user command that turns a file into searchable text, like pdftotext or a sqlite dump.
+minigrep runs `command <path>` with the file on stdin as well, and searches what it prints. */
+#[derive(Debug, Clone)]
+pub struct Preprocessor {
+    pub command: String,
+    /* Only files matching one of these globs go through the command, None means every file. */
+    globs: Option<GlobSet>,
+}
+
+impl Preprocessor {
+    pub fn build(command: String, globs: &[String]) -> Result<Preprocessor, &'static str> {
+        if globs.is_empty() {
+            return Ok(Preprocessor { command, globs: None });
+        }
+
+        let mut builder = GlobSetBuilder::new();
+        for glob in globs {
+            builder.add(Glob::new(glob).map_err(|_| "Invalid --pre-glob pattern")?);
+        }
+        let globs = builder.build().map_err(|_| "Invalid --pre-glob pattern")?;
+
+        Ok(Preprocessor { command, globs: Some(globs) })
+    }
+
+    pub fn applies_to(&self, path: &str) -> bool {
+        match &self.globs {
+            Some(globs) => globs.is_match(path),
+            None => true,
+        }
+    }
+
+    pub fn run(&self, path: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+        let output = Command::new(&self.command)
+            .arg(path)
+            .stdin(Stdio::from(File::open(path)?))
+            .output()
+            .map_err(|e| format!("could not run {}: {e}", self.command))?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("{} failed ({}): {}", self.command, output.status, stderr.trim()).into());
+        }
+
+        Ok(output.stdout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn globs_limit_the_files() {
+        let preprocessor = Preprocessor::build("cat".to_string(), &["*.pdf".to_string()]).unwrap();
+
+        assert!(preprocessor.applies_to("docs/manual.pdf"));
+        assert!(!preprocessor.applies_to("poem.txt"));
+        assert!(Preprocessor::build("cat".to_string(), &[]).unwrap().applies_to("poem.txt"));
+    }
+
+    #[test]
+    fn searches_the_command_output() {
+        let preprocessor = Preprocessor::build("cat".to_string(), &[]).unwrap();
+        let output = preprocessor.run("poem.txt").unwrap();
+
+        assert!(String::from_utf8(output).unwrap().starts_with("I'm nobody!"));
+        assert!(Preprocessor::build("false".to_string(), &[]).unwrap().run("poem.txt").is_err());
+    }
+}
@@ -0,0 +1,421 @@
+use std::borrow::Cow;
+use std::env;
+use std::io::{self, Write};
+use std::ops::Range;
+use std::path::MAIN_SEPARATOR;
+use std::sync::Mutex;
+
+use serde_json::json;
+
+/* How results are laid out, mostly for editors that read minigrep's output.
+- vimgrep: one `path:line:column:text` line for every match, like grep -n with a column.
+- null: a NUL byte instead of the separator that follows a path.
+- heading: the path once above its results, instead of in front of every result.
+- path_separator: printed instead of the platform's separator between path components.
+- each_match: one `line:column:text` row for every match, instead of one row for every line.
+- count_matches: only the number of matches in every file, not the matches themselves.
+- column_unit: whether columns count bytes or characters.
+- color: whether matches are highlighted, never in vimgrep and JSON output.
+- max_columns: lines longer than this many bytes are replaced with a summary, or with the text
+  around their matches when max_columns_preview is set.
+- json: one JSON object for every result, with the path in it, for programs to read. */
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OutputOptions {
+    pub vimgrep: bool,
+    pub null: bool,
+    pub heading: bool,
+    pub path_separator: Option<String>,
+    pub each_match: bool,
+    pub count_matches: bool,
+    pub column_unit: ColumnUnit,
+    pub color: ColorChoice,
+    pub max_columns: Option<usize>,
+    pub max_columns_preview: bool,
+    pub json: bool,
+}
+
+/* What --color asked for. Auto only means colours when stdout is a terminal and NO_COLOR is
+not set, run settles it with resolve before printing. A printer left with Auto, like the one
+minigrep serve writes to a connection with, prints no colours. */
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ColorChoice {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    pub fn parse(when: &str) -> Result<ColorChoice, &'static str> {
+        match when {
+            "auto" => Ok(ColorChoice::Auto),
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            _ => Err("Unknown --color, expected auto, always or never"),
+        }
+    }
+
+    pub fn resolve(self, terminal: bool) -> ColorChoice {
+        match self {
+            ColorChoice::Auto if terminal && env::var_os("NO_COLOR").is_none() => ColorChoice::Always,
+            ColorChoice::Auto => ColorChoice::Never,
+            chosen => chosen,
+        }
+    }
+}
+
+/* Bold red around every match, like grep and ripgrep. */
+const MATCH_COLOR: &str = "\x1b[1;31m";
+const RESET: &str = "\x1b[0m";
+
+/* Editors want byte columns, people counting along a line with accents in it want characters. */
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ColumnUnit {
+    #[default]
+    Bytes,
+    Chars,
+}
+
+impl ColumnUnit {
+    pub fn parse(unit: &str) -> Result<ColumnUnit, &'static str> {
+        match unit {
+            "bytes" => Ok(ColumnUnit::Bytes),
+            "chars" => Ok(ColumnUnit::Chars),
+            _ => Err("Unknown column unit, expected bytes or chars"),
+        }
+    }
+
+    /* The 1-based column of the match that starts at byte `start` of the line. */
+    pub fn column(self, line: &str, start: usize) -> usize {
+        match self {
+            ColumnUnit::Bytes => start + 1,
+            ColumnUnit::Chars => line[..start].chars().count() + 1,
+        }
+    }
+}
+
+/* One line that matched, with everything the output formats might need. */
+pub struct Line<'a> {
+    pub number: usize,
+    pub text: &'a str,
+    /* What ended the line in the file, \n or \r\n, empty for a last line without one. */
+    pub ending: &'a str,
+    pub offset: Option<usize>,
+    /* The 1-based column of every match in the line, in the chosen unit. */
+    pub columns: Vec<usize>,
+    /* The byte range of every match in text, in order, for highlighting. */
+    pub matches: Vec<Range<usize>>,
+}
+
+impl Line<'_> {
+    /* The line's own ending, so a file is printed with the endings it has. A last line without
+    one still gets a \n, or the next result would be printed on the same line. */
+    fn end(&self) -> &str {
+        if self.ending.is_empty() { "\n" } else { self.ending }
+    }
+}
+
+/* Turns results into text. Formatting one file is done without touching the output, so
+several threads can share one printer, format at the same time and only take turns printing.
+The output is stdout for a normal run and the connection for minigrep serve. */
+pub struct Printer {
+    options: OutputOptions,
+    /* The output, together with the number of groups printed to it so far and whether the last
+    one was standard input, which print_streamed can still add to. */
+    output: Mutex<(Box<dyn Write + Send>, usize, bool)>,
+}
+
+impl Printer {
+    pub fn new(options: OutputOptions, output: Box<dyn Write + Send>) -> Printer {
+        Printer { options, output: Mutex::new((output, 0, false)) }
+    }
+
+    pub fn format_lines(&self, name: &str, show_name: bool, lines: &[Line]) -> String {
+        if self.options.json {
+            let mut group = String::new();
+            for line in lines {
+                let mut row = json!({
+                    "path": self.display_path(name),
+                    "line": line.number,
+                    "columns": line.columns,
+                    "text": line.text,
+                });
+                if let Some(offset) = line.offset {
+                    row["offset"] = json!(offset);
+                }
+                group.push_str(&format!("{row}\n"));
+            }
+            return group;
+        }
+        if self.options.vimgrep {
+            let path = self.path(name);
+            let mut group = String::new();
+            for line in lines {
+                for column in &line.columns {
+                    group.push_str(&format!("{path}{}:{column}:{}{}", line.number, self.shown(line), line.end()));
+                }
+            }
+            return group;
+        }
+
+        let mut rows = Vec::new();
+        for line in lines {
+            let prefix = match line.offset {
+                Some(offset) => format!("{offset}:"),
+                None => String::new(),
+            };
+            if self.options.each_match {
+                for column in &line.columns {
+                    rows.push(format!("{prefix}{}:{column}:{}{}", line.number, self.shown(line), line.end()));
+                }
+            } else {
+                rows.push(format!("{prefix}{}{}", self.shown(line), line.end()));
+            }
+        }
+        self.format_group(name, show_name, rows.into_iter())
+    }
+
+    /* What --count-matches prints for one file. Files without matches are left out. */
+    pub fn format_count(&self, name: &str, show_name: bool, count: usize) -> String {
+        match (count, show_name) {
+            (0, _) => String::new(),
+            _ if self.options.json => format!("{}\n", json!({ "path": self.display_path(name), "count": count })),
+            (_, true) => format!("{}{count}\n", self.path(name)),
+            (_, false) => format!("{count}\n"),
+        }
+    }
+
+    pub fn format_records(&self, name: &str, show_name: bool, records: &[String]) -> String {
+        if self.options.json {
+            return records
+                .iter()
+                .map(|record| format!("{}\n", json!({ "path": self.display_path(name), "record": record })))
+                .collect();
+        }
+        self.format_group(name, show_name, records.iter().map(|record| format!("{record}\n")))
+    }
+
+    /* Prints what format_lines or format_records made of one file. With headings, groups are
+    kept apart by an empty line. */
+    pub fn print(&self, group: &str) -> io::Result<()> {
+        if group.is_empty() {
+            return Ok(());
+        }
+        /* Holding the lock keeps other threads from printing between the gap and the group. */
+        let mut output = self.output.lock().unwrap();
+        let (writer, printed, streaming) = &mut *output;
+        if self.gaps() && *printed > 0 {
+            writer.write_all(b"\n")?;
+        }
+        *printed += 1;
+        *streaming = false;
+        writer.write_all(group.as_bytes())?;
+        writer.flush()
+    }
+
+    /* Prints the lines of standard input as they are read. They are all one group, so the gap
+    and the heading only come before the first of them, or again when another file was printed
+    in between. */
+    pub fn print_streamed(&self, name: &str, show_name: bool, lines: &[Line]) -> io::Result<()> {
+        if lines.is_empty() {
+            return Ok(());
+        }
+        let mut output = self.output.lock().unwrap();
+        let (writer, printed, streaming) = &mut *output;
+        let group = match *streaming {
+            /* Without a heading every row has its path in front, so that is not left out. */
+            true => self.format_lines(name, show_name && !self.options.heading, lines),
+            false => {
+                if self.gaps() && *printed > 0 {
+                    writer.write_all(b"\n")?;
+                }
+                *printed += 1;
+                *streaming = true;
+                self.format_lines(name, show_name, lines)
+            }
+        };
+        writer.write_all(group.as_bytes())?;
+        writer.flush()
+    }
+
+    /* With headings, groups are kept apart by an empty line. */
+    fn gaps(&self) -> bool {
+        self.options.heading && !self.options.vimgrep && !self.options.json
+    }
+
+    /* The results of one file, with its path in front of every row or once above all of them.
+    Every row already ends the way its line did. */
+    fn format_group(&self, name: &str, show_name: bool, rows: impl ExactSizeIterator<Item = String>) -> String {
+        let mut group = String::new();
+        if rows.len() == 0 {
+            return group;
+        }
+
+        if show_name && self.options.heading {
+            let terminator = if self.options.null { '\0' } else { '\n' };
+            group.push_str(&format!("{}{terminator}", self.display_path(name)));
+            for row in rows {
+                group.push_str(&row);
+            }
+        } else {
+            let prefix = if show_name { self.path(name) } else { String::new() };
+            for row in rows {
+                group.push_str(&format!("{prefix}{row}"));
+            }
+        }
+        group
+    }
+
+    /* What is printed of the line: all of it, or for a line longer than --max-columns a
+    summary, or the text around its matches with [...] where something was left out. */
+    fn shown<'t>(&self, line: &Line<'t>) -> Cow<'t, str> {
+        let Some(max) = self.options.max_columns.filter(|&max| line.text.len() > max) else {
+            return self.highlight(line, 0..line.text.len());
+        };
+        if !self.options.max_columns_preview {
+            return Cow::Owned(format!("[omitted long line with {} matches]", line.columns.len()));
+        }
+
+        let mut shown = String::new();
+        let mut end = 0;
+        for window in windows(line, max) {
+            if window.start > end {
+                shown.push_str("[...]");
+            }
+            end = window.end;
+            shown.push_str(&self.highlight(line, window));
+        }
+        if end < line.text.len() {
+            shown.push_str("[...]");
+        }
+        Cow::Owned(shown)
+    }
+
+    /* The part of the line in shown, with colour codes around its matches when colours are on. */
+    fn highlight<'t>(&self, line: &Line<'t>, shown: Range<usize>) -> Cow<'t, str> {
+        if self.options.color != ColorChoice::Always || line.matches.is_empty() {
+            return Cow::Borrowed(&line.text[shown]);
+        }
+        let mut text = String::new();
+        let mut last = shown.start;
+        for range in &line.matches {
+            /* Empty matches, like the one of an empty query, have nothing to colour. */
+            let (start, end) = (range.start.max(last), range.end.min(shown.end));
+            if start >= end {
+                continue;
+            }
+            text.push_str(&line.text[last..start]);
+            text.push_str(&format!("{MATCH_COLOR}{}{RESET}", &line.text[start..end]));
+            last = end;
+        }
+        text.push_str(&line.text[last..shown.end]);
+        Cow::Owned(text)
+    }
+
+    /* The path with whatever has to follow it in front of a result. */
+    fn path(&self, name: &str) -> String {
+        let terminator = if self.options.null { '\0' } else { ':' };
+        format!("{}{terminator}", self.display_path(name))
+    }
+
+    fn display_path(&self, name: &str) -> String {
+        match &self.options.path_separator {
+            Some(separator) => name.replace(MAIN_SEPARATOR, separator),
+            None => name.to_string(),
+        }
+    }
+}
+
+/* The parts --max-columns-preview shows of a long line: max bytes around every match, on
+character boundaries and merged where they overlap. A line without matches shows its start. */
+fn windows(line: &Line, max: usize) -> Vec<Range<usize>> {
+    let text = line.text;
+    let floor = |mut index: usize| {
+        while !text.is_char_boundary(index) {
+            index -= 1;
+        }
+        index
+    };
+    let ceil = |mut index: usize| {
+        while !text.is_char_boundary(index) {
+            index += 1;
+        }
+        index
+    };
+    if line.matches.is_empty() {
+        return std::iter::once(0..floor(max)).collect();
+    }
+
+    let mut windows: Vec<Range<usize>> = Vec::new();
+    for found in &line.matches {
+        let context = max.saturating_sub(found.len()) / 2;
+        let window = floor(found.start.saturating_sub(context))..ceil((found.end + context).min(text.len()));
+        match windows.last_mut() {
+            Some(last) if window.start <= last.end => last.end = last.end.max(window.end),
+            _ => windows.push(window),
+        }
+    }
+    windows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paths_use_the_chosen_separator_and_terminator() {
+        let options = OutputOptions {
+            null: true,
+            path_separator: Some("\\".to_string()),
+            ..OutputOptions::default()
+        };
+        let printer = Printer::new(options, Box::new(io::sink()));
+
+        assert_eq!("src\\lib.rs\0", printer.path(&format!("src{MAIN_SEPARATOR}lib.rs")));
+    }
+
+    #[test]
+    fn highlights_matches_only_with_colours_on() {
+        let line = Line {
+            number: 1,
+            text: "error: disk error",
+            ending: "\n",
+            offset: None,
+            columns: vec![1, 13],
+            matches: vec![0..5, 12..17],
+        };
+        let options = OutputOptions { color: ColorChoice::Always, ..OutputOptions::default() };
+        let printer = Printer::new(options, Box::new(io::sink()));
+        assert_eq!("\x1b[1;31merror\x1b[0m: disk \x1b[1;31merror\x1b[0m", printer.shown(&line));
+
+        let printer = Printer::new(OutputOptions::default(), Box::new(io::sink()));
+        assert_eq!("error: disk error", printer.shown(&line));
+    }
+
+    #[test]
+    fn long_lines_are_summed_up_or_previewed() {
+        let text = format!("{}needle{}needle{}", "a".repeat(20), "b".repeat(4), "c".repeat(20));
+        let line = Line {
+            number: 1,
+            text: &text,
+            ending: "\n",
+            offset: None,
+            columns: vec![21, 31],
+            matches: vec![20..26, 30..36],
+        };
+        let options = OutputOptions { max_columns: Some(10), ..OutputOptions::default() };
+        let printer = Printer::new(options.clone(), Box::new(io::sink()));
+        assert_eq!("[omitted long line with 2 matches]", printer.shown(&line));
+
+        let printer = Printer::new(OutputOptions { max_columns_preview: true, ..options }, Box::new(io::sink()));
+        assert_eq!("[...]aaneedlebbbbneedlecc[...]", printer.shown(&line));
+    }
+
+    #[test]
+    fn columns_count_bytes_or_chars() {
+        let line = "café au lait";
+        let start = line.find("au").unwrap();
+        assert_eq!(7, ColumnUnit::Bytes.column(line, start));
+        assert_eq!(6, ColumnUnit::Chars.column(line, start));
+    }
+}
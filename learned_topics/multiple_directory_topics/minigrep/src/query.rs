@@ -0,0 +1,269 @@
+use std::error::Error;
+use std::ops::Range;
+
+use crate::matcher::{self, Engine, Matcher};
+
+/* A --boolean query like `(panic OR abort) AND NOT timeout`, parsed into a tree. The terms
+start out as the words of the query, and are turned into matchers once it is known whether
+the search ignores case and which engine it uses. NOT binds tighter than AND, and AND tighter than OR. */
+#[derive(Debug, Clone, PartialEq)]
+pub enum Query<T> {
+    Term(T),
+    Not(Box<Query<T>>),
+    And(Box<Query<T>>, Box<Query<T>>),
+    Or(Box<Query<T>>, Box<Query<T>>),
+}
+
+impl Query<String> {
+    pub fn parse(query: &str) -> Result<Query<String>, &'static str> {
+        let tokens = tokenize(query)?;
+        let mut parser = Parser { tokens, next: 0 };
+        let tree = parser.or(0)?;
+        match parser.tokens.get(parser.next) {
+            None => Ok(tree),
+            Some(Token::Close) => Err("Unbalanced parentheses in the query"),
+            Some(_) => Err("Expected AND or OR between the terms of the query"),
+        }
+    }
+
+    /* Builds a matcher for every term, the same way a plain query would be built. */
+    pub fn build(self, ignore_case: bool, engine: Engine) -> Result<Query<Box<dyn Matcher>>, Box<dyn Error>> {
+        Ok(match self {
+            Query::Term(term) => Query::Term(matcher::build(&term, ignore_case, engine)?),
+            Query::Not(inner) => Query::Not(Box::new(inner.build(ignore_case, engine)?)),
+            Query::And(left, right) => Query::And(
+                Box::new(left.build(ignore_case, engine)?),
+                Box::new(right.build(ignore_case, engine)?),
+            ),
+            Query::Or(left, right) => Query::Or(
+                Box::new(left.build(ignore_case, engine)?),
+                Box::new(right.build(ignore_case, engine)?),
+            ),
+        })
+    }
+}
+
+impl Query<Box<dyn Matcher>> {
+    /* None when the text does not satisfy the query, otherwise the matches of the terms that
+    made it true. Terms under a NOT never add matches, there is nothing to point at. */
+    fn evaluate(&self, text: &str) -> Result<Option<Vec<Range<usize>>>, Box<dyn Error>> {
+        Ok(match self {
+            Query::Term(matcher) => Some(matcher.find_iter(text)?).filter(|found| !found.is_empty()),
+            Query::Not(inner) => match inner.evaluate(text)? {
+                Some(_) => None,
+                None => Some(Vec::new()),
+            },
+            Query::And(left, right) => {
+                let Some(mut found) = left.evaluate(text)? else {
+                    return Ok(None);
+                };
+                let Some(right) = right.evaluate(text)? else {
+                    return Ok(None);
+                };
+                found.extend(right);
+                Some(found)
+            }
+            Query::Or(left, right) => match (left.evaluate(text)?, right.evaluate(text)?) {
+                (None, None) => None,
+                (left, right) => Some(left.into_iter().chain(right).flatten().collect()),
+            },
+        })
+    }
+}
+
+impl Matcher for Query<Box<dyn Matcher>> {
+    fn find_iter(&self, line: &str) -> Result<Vec<Range<usize>>, Box<dyn Error>> {
+        let Some(mut found) = self.evaluate(line)? else {
+            return Ok(Vec::new());
+        };
+        /* A query like `NOT timeout` matches without a match to show, that counts as an empty
+        match at the start, like an empty query. */
+        if found.is_empty() {
+            return Ok(std::iter::once(0..0).collect());
+        }
+
+        /* Terms are searched one by one, so their matches have to be put in order and the ones
+        overlapping an earlier match dropped. */
+        found.sort_by_key(|range| (range.start, range.end));
+        let mut kept: Vec<Range<usize>> = Vec::with_capacity(found.len());
+        for range in found {
+            if kept.last().is_none_or(|last| range.start >= last.end) {
+                kept.push(range);
+            }
+        }
+        Ok(kept)
+    }
+
+    fn is_match(&self, line: &str) -> Result<bool, Box<dyn Error>> {
+        Ok(self.evaluate(line)?.is_some())
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Word(String),
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+/* Splits on whitespace and parentheses. AND, OR and NOT are only operators when written in
+capitals, and "double quotes" make a term of anything, spaces and operators included. */
+fn tokenize(query: &str) -> Result<Vec<Token>, &'static str> {
+    let mut tokens = Vec::new();
+    let mut chars = query.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '(' || c == ')' {
+            chars.next();
+            tokens.push(if c == '(' { Token::Open } else { Token::Close });
+        } else if c == '"' {
+            chars.next();
+            let mut word = String::new();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some(c) => word.push(c),
+                    None => return Err("Unclosed quote in the query"),
+                }
+            }
+            tokens.push(Token::Word(word));
+        } else {
+            let mut word = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() || c == '(' || c == ')' || c == '"' {
+                    break;
+                }
+                word.push(c);
+                chars.next();
+            }
+            tokens.push(match word.as_str() {
+                "AND" => Token::And,
+                "OR" => Token::Or,
+                "NOT" => Token::Not,
+                _ => Token::Word(word),
+            });
+        }
+    }
+
+    Ok(tokens)
+}
+
+/* How deep parentheses, NOTs and chains of AND and OR can go. Every level is a call when the
+query is parsed, evaluated and dropped, so without a limit a long enough query runs out of stack. */
+const MAX_DEPTH: usize = 256;
+
+/* A recursive descent parser, one method for every level of precedence. depth is how deep in
+the tree the part being parsed ends up. */
+struct Parser {
+    tokens: Vec<Token>,
+    next: usize,
+}
+
+impl Parser {
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.tokens.get(self.next) == Some(token) {
+            self.next += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn or(&mut self, mut depth: usize) -> Result<Query<String>, &'static str> {
+        let mut tree = self.and(depth)?;
+        while self.eat(&Token::Or) {
+            depth = deeper(depth)?;
+            tree = Query::Or(Box::new(tree), Box::new(self.and(depth)?));
+        }
+        Ok(tree)
+    }
+
+    fn and(&mut self, mut depth: usize) -> Result<Query<String>, &'static str> {
+        let mut tree = self.not(depth)?;
+        while self.eat(&Token::And) {
+            depth = deeper(depth)?;
+            tree = Query::And(Box::new(tree), Box::new(self.not(depth)?));
+        }
+        Ok(tree)
+    }
+
+    fn not(&mut self, depth: usize) -> Result<Query<String>, &'static str> {
+        if self.eat(&Token::Not) {
+            return Ok(Query::Not(Box::new(self.not(deeper(depth)?)?)));
+        }
+        self.term(depth)
+    }
+
+    fn term(&mut self, depth: usize) -> Result<Query<String>, &'static str> {
+        if self.eat(&Token::Open) {
+            let tree = self.or(deeper(depth)?)?;
+            if !self.eat(&Token::Close) {
+                return Err("Unbalanced parentheses in the query");
+            }
+            return Ok(tree);
+        }
+        match self.tokens.get(self.next) {
+            Some(Token::Word(word)) => {
+                let term = Query::Term(word.clone());
+                self.next += 1;
+                Ok(term)
+            }
+            _ => Err("Expected a search term in the query"),
+        }
+    }
+}
+
+fn deeper(depth: usize) -> Result<usize, &'static str> {
+    match depth < MAX_DEPTH {
+        true => Ok(depth + 1),
+        false => Err("Query is nested too deeply"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn term(word: &str) -> Box<Query<String>> {
+        Box::new(Query::Term(word.to_string()))
+    }
+
+    #[test]
+    fn not_binds_tighter_than_and_and_and_tighter_than_or() {
+        assert_eq!(
+            Query::Or(term("panic"), Box::new(Query::And(term("abort"), Box::new(Query::Not(term("timeout")))))),
+            Query::parse("panic OR abort AND NOT timeout").unwrap()
+        );
+        assert_eq!(
+            Query::And(Box::new(Query::Or(term("panic"), term("abort"))), term("worker")),
+            Query::parse("(panic OR abort) AND \"worker\"").unwrap()
+        );
+        assert!(Query::parse("(panic OR abort").is_err());
+        assert!(Query::parse("panic abort").is_err());
+        assert!(Query::parse("panic AND").is_err());
+    }
+
+    #[test]
+    fn deep_queries_are_an_error_not_a_crash() {
+        let nested = |depth: usize| format!("{}error{}", "(".repeat(depth), ")".repeat(depth));
+        assert!(Query::parse(&nested(MAX_DEPTH)).is_ok());
+        assert_eq!(Err("Query is nested too deeply"), Query::parse(&nested(50_000)));
+        assert_eq!(Err("Query is nested too deeply"), Query::parse(&format!("{}error", "NOT ".repeat(30_000))));
+        assert_eq!(Err("Query is nested too deeply"), Query::parse(&vec!["error"; 30_000].join(" AND ")));
+    }
+
+    #[test]
+    fn matches_come_from_the_terms_that_made_it_true() {
+        let query = Query::parse("error AND NOT timeout").unwrap().build(false, Engine::Literal).unwrap();
+        assert_eq!(vec![3..8, 13..18], query.find_iter("an error, an error").unwrap());
+        assert!(!query.is_match("error: timeout").unwrap());
+
+        let query = Query::parse("NOT timeout").unwrap().build(false, Engine::Literal).unwrap();
+        assert_eq!(vec![0..0], query.find_iter("all good").unwrap());
+    }
+}
@@ -0,0 +1,136 @@
+use std::error::Error;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+
+use toml_edit::{table, value, Array, DocumentMut, Item};
+
+/* Saved searches live in this file in the current directory, so a team can commit it next to
+the runbooks that use them. Every search is a table with the arguments it runs with:
+
+    [searches.disk-full]
+    args = ["--regex", "disk (full|error)", "logs"]
+
+The file is edited in place, so comments and the order of the searches survive a save. */
+pub const FILE: &str = ".minigrep.toml";
+
+/* A search to save with `minigrep save <name> -- <args>`. */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Save {
+    pub name: String,
+    pub args: Vec<String>,
+}
+
+impl Save {
+    /* Takes the arguments after `save`. The -- after the name is optional, everything after it
+    belongs to the search. */
+    pub fn parse(args: &[String]) -> Result<Save, &'static str> {
+        let (name, rest) = args.split_first().ok_or("minigrep save needs a name")?;
+        let valid = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_';
+        if name.is_empty() || !name.chars().all(valid) {
+            return Err("A saved search needs a name of letters, digits, - and _");
+        }
+        let rest = match rest.first() {
+            Some(arg) if arg == "--" => &rest[1..],
+            _ => rest,
+        };
+        Ok(Save { name: name.clone(), args: rest.to_vec() })
+    }
+
+    /* The command line the search runs with, for Config::build to check. */
+    pub fn command_line(&self) -> Vec<String> {
+        std::iter::once(String::from("minigrep")).chain(self.args.iter().cloned()).collect()
+    }
+
+    /* Adds the search to the file, or replaces the one with the same name. */
+    pub fn write(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let mut document = read(path)?;
+        let searches = document
+            .entry("searches")
+            .or_insert(table())
+            .as_table_mut()
+            .ok_or_else(|| format!("{}: searches has to be a table", path.display()))?;
+        /* Only the [searches.<name>] headers are written, not an empty [searches] above them. */
+        searches.set_implicit(true);
+
+        /* A search that is already there keeps its place and the comments around it. */
+        let args = value(Array::from_iter(&self.args));
+        match searches.get_mut(&self.name).and_then(Item::as_table_mut) {
+            Some(search) => search["args"] = args,
+            None => {
+                let mut search = table();
+                search["args"] = args;
+                searches.insert(&self.name, search);
+            }
+        }
+        fs::write(path, document.to_string())?;
+        Ok(())
+    }
+}
+
+/* Replaces `minigrep run <name> [args]` with the saved arguments, and any given after the name
+at the end of them. Every other command line comes back as it is. */
+pub fn expand(args: Vec<String>) -> Result<Vec<String>, Box<dyn Error>> {
+    if args.get(1).is_none_or(|arg| arg != "run") {
+        return Ok(args);
+    }
+    let name = args.get(2).ok_or("minigrep run needs the name of a saved search")?;
+    let saved = load(Path::new(FILE), name)?;
+    Ok(args[..1].iter().cloned().chain(saved).chain(args[3..].iter().cloned()).collect())
+}
+
+fn load(path: &Path, name: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let document = read(path)?;
+    let search = document
+        .get("searches")
+        .and_then(|searches| searches.get(name))
+        .ok_or_else(|| format!("there is no saved search named {name} in {}", path.display()))?;
+    let args = search
+        .get("args")
+        .and_then(Item::as_array)
+        .ok_or_else(|| format!("{}: the search {name} has no args list", path.display()))?;
+    args.iter()
+        .map(|arg| {
+            let arg = arg.as_str().ok_or_else(|| format!("{}: the args of {name} have to be strings", path.display()));
+            Ok(arg?.to_string())
+        })
+        .collect()
+}
+
+/* A file that does not exist yet has no searches in it. */
+fn read(path: &Path) -> Result<DocumentMut, Box<dyn Error>> {
+    match fs::read_to_string(path) {
+        Ok(text) => Ok(text.parse().map_err(|e| format!("{}: {e}", path.display()))?),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(DocumentMut::new()),
+        Err(e) => Err(format!("{}: {e}", path.display()).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn saves_next_to_what_is_there() {
+        let dir = env::temp_dir().join(format!("minigrep-saved-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(FILE);
+        fs::write(&path, "# Searches from the on-call runbook.\n[searches.old]\nargs = [\"old\", \"logs\"]\n").unwrap();
+
+        Save::parse(&args(&["disk-full", "--", "--regex", "disk (full|error)", "logs"])).unwrap().write(&path).unwrap();
+        Save::parse(&args(&["old", "new", "logs"])).unwrap().write(&path).unwrap();
+
+        let text = fs::read_to_string(&path).unwrap();
+        assert!(text.starts_with("# Searches from the on-call runbook.\n[searches.old]\n"));
+        assert_eq!(args(&["--regex", "disk (full|error)", "logs"]), load(&path, "disk-full").unwrap());
+        assert_eq!(args(&["new", "logs"]), load(&path, "old").unwrap());
+        assert!(load(&path, "missing").is_err());
+        assert!(Save::parse(&args(&["no spaces", "x"])).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,345 @@
+use std::error::Error;
+use std::io::{self, BufRead, Read};
+use std::ops::Range;
+use std::time::Instant;
+
+use crate::aggregate::Histogram;
+use crate::encoding::{self, Decoded};
+use crate::input::{self, Source};
+use crate::lexer::{self, Language};
+use crate::matcher::Matcher;
+use crate::printer::{ColumnUnit, Line, Printer};
+use crate::stats::Stats;
+use crate::{archive, Config};
+
+/* What a searcher has to report once its files are done.
+- errors: one message for every path that could not be searched, with the path in front.
+- histogram: the --group-by values, empty without it.
+- timed_out: the --timeout passed before every file was searched. */
+#[derive(Debug, Default)]
+pub(crate) struct Outcome {
+    pub(crate) stats: Stats,
+    pub(crate) errors: Vec<String>,
+    pub(crate) histogram: Histogram,
+    pub(crate) timed_out: bool,
+}
+
+impl Outcome {
+    /* Takes in what another searcher of this run found: its numbers, errors and values. */
+    pub(crate) fn add(&mut self, other: Outcome) {
+        self.stats.add(&other.stats);
+        self.errors.extend(other.errors);
+        self.histogram.merge(other.histogram);
+        self.timed_out |= other.timed_out;
+    }
+}
+
+/* Searches files one after the other and keeps count while doing so. run uses one of these
+when the order matters, and one per thread when it does not. */
+pub(crate) struct Searcher<'a> {
+    config: &'a Config,
+    printer: &'a Printer,
+    matcher: &'a dyn Matcher,
+    outcome: Outcome,
+    /* Whether the last timestamp --since and --until saw was inside the window. */
+    in_window: bool,
+}
+
+/* Where a piece of text starts in its file. Files are searched in one piece that starts at
+the beginning, standard input a line at a time. */
+#[derive(Debug, Clone, Copy, Default)]
+struct Start {
+    line: usize,
+    byte: usize,
+}
+
+impl<'a> Searcher<'a> {
+    pub(crate) fn new(config: &'a Config, printer: &'a Printer, matcher: &'a dyn Matcher) -> Searcher<'a> {
+        Searcher {
+            config,
+            printer,
+            matcher,
+            outcome: Outcome::default(),
+            in_window: false,
+        }
+    }
+
+    pub(crate) fn finish(self) -> Outcome {
+        self.outcome
+    }
+
+    /* Stops taking new files once the deadline has passed, a file being searched is finished. */
+    pub(crate) fn timed_out(&mut self, deadline: Option<Instant>) -> bool {
+        self.outcome.timed_out |= deadline.is_some_and(|deadline| Instant::now() >= deadline);
+        self.outcome.timed_out
+    }
+
+    /* A path that cannot be searched does not stop the others, its error is kept for the end. */
+    pub(crate) fn search_source(&mut self, source: &Source, show_name: bool) {
+        let searched = match &source.blob {
+            Some(bytes) => self.search_bytes(&source.path, bytes, show_name, source.named),
+            None => self.search_path(&source.path, show_name, source.named),
+        };
+        if let Err(e) = searched {
+            self.outcome.errors.push(format!("{}: {e}", source.path));
+        }
+    }
+
+    fn search_path(&mut self, path: &str, show_name: bool, named: bool) -> Result<(), Box<dyn Error>> {
+        let config = self.config;
+        if path == "-" {
+            return self.search_stdin(show_name);
+        }
+
+        /* Every file inside an archive is searched as if it was a file of its own, found in a
+        directory: binary members are skipped, and a member that fails does not stop the rest. */
+        if config.search_archives && archive::is_archive(path) {
+            return archive::for_each_member(path, config.max_filesize, |member, bytes| {
+                let name = format!("{path}!{member}");
+                if let Err(e) = self.search_bytes(&name, bytes, true, false) {
+                    self.outcome.errors.push(format!("{name}: {e}"));
+                }
+                Ok(())
+            });
+        }
+
+        if let Some(preprocessor) = config.preprocessor.as_ref().filter(|pre| pre.applies_to(path)) {
+            let output = preprocessor.run(path)?;
+            return self.search_bytes(path, &output, show_name, true);
+        }
+
+        /* Gets the raw bytes first, so files that are not UTF-8 can be decoded as well. Large
+        files are memory mapped instead of copied. */
+        let bytes = input::open(path, config.mmap)?;
+        self.search_bytes(path, &bytes, show_name, named)
+    }
+
+    /* Standard input is searched a line at a time and every result is printed as soon as its
+    line is read, so minigrep can sit at the end of `tail -f`. Searches that need all of the
+    input first, like --block, --count-matches or --only, or that decode it, read all of it
+    instead. So does UTF-16 input, which is recognized by the byte order mark on its first line. */
+    fn search_stdin(&mut self, show_name: bool) -> Result<(), Box<dyn Error>> {
+        let config = self.config;
+        let mut stdin = io::stdin().lock();
+        let mut buffer = Vec::new();
+        stdin.read_until(b'\n', &mut buffer)?;
+        let streams = !config.block
+            && config.structured.is_none()
+            && config.group_by.is_none()
+            && config.code_filter.is_none()
+            && !config.output.count_matches
+            && config.encoding.is_none()
+            && encoding::splits_into_lines(&buffer);
+        if !streams {
+            stdin.read_to_end(&mut buffer)?;
+            return self.search_bytes("-", &buffer, show_name, true);
+        }
+
+        self.outcome.stats.files_searched += 1;
+        self.in_window = false;
+        let mut start = Start::default();
+        let mut matched = 0;
+        while !buffer.is_empty() {
+            let decoded = encoding::decode(&buffer, None)?;
+            let lines = self.match_lines("-", &decoded, start)?;
+            matched += self.matched(&lines);
+            self.printer.print_streamed("-", show_name, &lines)?;
+
+            self.outcome.stats.bytes_read += buffer.len() as u64;
+            self.outcome.stats.lines_scanned += 1;
+            start.line += 1;
+            start.byte += buffer.len();
+            buffer.clear();
+            stdin.read_until(b'\n', &mut buffer)?;
+        }
+        self.count_matches(matched);
+        Ok(())
+    }
+
+    fn search_bytes(&mut self, name: &str, bytes: &[u8], show_name: bool, named: bool) -> Result<(), Box<dyn Error>> {
+        let config = self.config;
+        let decoded = match encoding::decode(bytes, config.encoding) {
+            Ok(decoded) => decoded,
+            /* Images and other binary files inside a directory are not worth an error. */
+            Err(_) if !named => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        let contents = &decoded.text;
+
+        self.outcome.stats.files_searched += 1;
+        self.outcome.stats.bytes_read += bytes.len() as u64;
+        self.outcome.stats.lines_scanned += contents.lines().count();
+
+        /* Structured input is split into records instead of lines, so it has its own search. */
+        if let Some(structured) = &config.structured {
+            let records = structured.search(self.matcher, contents, config.ignore_case)?;
+            self.count_matches(records.len());
+            if config.output.count_matches {
+                self.printer.print(&self.printer.format_count(name, show_name, records.len()))?;
+            } else {
+                self.printer.print(&self.printer.format_records(name, show_name, &records))?;
+            }
+            return Ok(());
+        }
+
+        self.in_window = false;
+        let lines = match config.block {
+            true => self.match_blocks(&decoded)?,
+            false => self.match_lines(name, &decoded, Start::default())?,
+        };
+        self.count_matches(self.matched(&lines));
+        if let Some(group) = &config.group_by {
+            /* Only the histogram is printed, once every file is done. */
+            for line in &lines {
+                for value in self.matcher.captures(line.text, group)? {
+                    self.outcome.histogram.add(value);
+                }
+            }
+        } else if config.output.count_matches {
+            /* Every match counts, not every line, a line can hold the query many times. */
+            let count = lines.iter().map(|line| line.columns.len()).sum();
+            self.printer.print(&self.printer.format_count(name, show_name, count))?;
+        } else {
+            self.printer.print(&self.printer.format_lines(name, show_name, &lines))?;
+        }
+
+        Ok(())
+    }
+
+    /* With --passthru every line is kept, the ones without a match have no columns. */
+    fn match_lines<'t>(&mut self, name: &str, decoded: &'t Decoded, start: Start) -> Result<Vec<Line<'t>>, Box<dyn Error>> {
+        let config = self.config;
+        let contents = &decoded.text;
+        let regions = match &config.code_filter {
+            Some(_) => lexer::regions(contents, Language::from_path(name)),
+            None => Vec::new(),
+        };
+
+        let mut lines = Vec::new();
+        for (index, (text, ending)) in split_lines(contents, config.crlf).enumerate() {
+            let mut found = Vec::new();
+            if let Some(window) = &config.time_window {
+                /* A line without a timestamp belongs to the last line that had one. */
+                if let Some(time) = window.timestamp(text) {
+                    self.in_window = window.contains(time);
+                }
+            }
+            if config.time_window.is_none() || self.in_window {
+                found = self.matcher.find_iter(text).map_err(|e| format!("line {}: {e}", start.line + index + 1))?;
+            }
+            if let Some(filter) = &config.code_filter {
+                let line_start = offset_in(contents, text);
+                found.retain(|range| filter.keeps(lexer::kind_at(&regions, line_start + range.start)));
+            }
+            if !found.is_empty() || config.passthru {
+                let starts: Vec<usize> = found.iter().map(|range| range.start).collect();
+                lines.push(self.line(decoded, start, index, (text, ending), found, starts));
+            }
+        }
+        Ok(lines)
+    }
+
+    /* A block is a run of lines without an empty one in between. When the query matches a
+    block, all of its lines are printed, and every match is shown on the line it starts in. */
+    fn match_blocks<'t>(&self, decoded: &'t Decoded) -> Result<Vec<Line<'t>>, Box<dyn Error>> {
+        let contents = &decoded.text;
+        let numbered: Vec<(usize, (&str, &str))> = split_lines(contents, self.config.crlf).enumerate().collect();
+
+        let mut lines = Vec::new();
+        for block in numbered.split(|(_, (text, _))| text.trim().is_empty()) {
+            let (Some(&(_, (first, _))), Some(&(_, (last, _)))) = (block.first(), block.last()) else {
+                continue;
+            };
+            let start = offset_in(contents, first);
+            let block_text = &contents[start..offset_in(contents, last) + last.len()];
+            let found = self.matcher.find_iter(block_text).map_err(|e| format!("line {}: {e}", block[0].0 + 1))?;
+            if found.is_empty() {
+                continue;
+            }
+
+            for &(index, (text, ending)) in block {
+                let line_start = offset_in(contents, text) - start;
+                let line_end = line_start + text.len();
+                let starts = found
+                    .iter()
+                    .map(|range| range.start)
+                    .filter(|&match_start| (line_start..=line_end).contains(&match_start))
+                    .map(|match_start| match_start - line_start)
+                    .collect();
+                /* The part of every match that lies on this line, to highlight. */
+                let matches = found
+                    .iter()
+                    .filter(|range| range.start < line_end && range.end > line_start)
+                    .map(|range| range.start.max(line_start) - line_start..range.end.min(line_end) - line_start)
+                    .collect();
+                lines.push(self.line(decoded, Start::default(), index, (text, ending), matches, starts));
+            }
+        }
+        Ok(lines)
+    }
+
+    /* index is the line's index in decoded, matches the byte ranges to highlight and starts
+    where the matches that get a column start. */
+    fn line<'t>(
+        &self,
+        decoded: &Decoded,
+        start: Start,
+        index: usize,
+        (text, ending): (&'t str, &'t str),
+        matches: Vec<Range<usize>>,
+        starts: Vec<usize>,
+    ) -> Line<'t> {
+        let config = self.config;
+        let line_start = offset_in(&decoded.text, text);
+        let offset = config.byte_offset.then(|| start.byte + decoded.original_offset(line_start));
+        /* Byte columns count the bytes of the original file, which are not the bytes of the
+        decoded text in UTF-16 or Latin-1. A character is one character in both. */
+        let column = |start: usize| match config.output.column_unit {
+            ColumnUnit::Bytes => decoded.original_offset(line_start + start) - decoded.original_offset(line_start) + 1,
+            unit => unit.column(text, start),
+        };
+        Line {
+            number: start.line + index + 1,
+            text,
+            ending,
+            offset,
+            columns: starts.iter().map(|&start| column(start)).collect(),
+            matches,
+        }
+    }
+
+    /* How many lines count as matches, --passthru also returns the lines without one. */
+    fn matched(&self, lines: &[Line]) -> usize {
+        match self.config.passthru {
+            true => lines.iter().filter(|line| !line.columns.is_empty()).count(),
+            false => lines.len(),
+        }
+    }
+
+    fn count_matches(&mut self, matches: usize) {
+        self.outcome.stats.matches += matches;
+        if matches > 0 {
+            self.outcome.stats.files_matched += 1;
+        }
+    }
+}
+
+/* Splits the text after every \n and keeps what ended each line, so the output can end it the
+same way. With --crlf a \r in front of the \n belongs to the ending and `$` matches before it,
+otherwise it is part of the line like any other character. The last line may have no ending. */
+fn split_lines(contents: &str, crlf: bool) -> impl Iterator<Item = (&str, &str)> {
+    contents.split_inclusive('\n').map(move |line| {
+        let ending = match line {
+            _ if crlf && line.ends_with("\r\n") => 2,
+            _ if line.ends_with('\n') => 1,
+            _ => 0,
+        };
+        line.split_at(line.len() - ending)
+    })
+}
+
+/* Every line is a slice of contents, so its distance to the start of contents is its offset
+in the decoded text. */
+fn offset_in(contents: &str, text: &str) -> usize {
+    text.as_ptr() as usize - contents.as_ptr() as usize
+}
@@ -0,0 +1,284 @@
+use std::error::Error;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde_json::json;
+
+use crate::printer::Printer;
+use crate::{search_with, Config};
+
+/* How many requests are answered at the same time, more get a 503 right away. */
+const MAX_CONNECTIONS: usize = 32;
+/* How long a client may keep a connection waiting, for its request or for reading the answer. */
+const IDLE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/* The longest request line or header that is read, and how many headers. Longer requests get
+a 414 or 431 instead of growing a buffer for as long as the client keeps sending. */
+const MAX_LINE: u64 = 8 * 1024;
+const MAX_HEADERS: usize = 100;
+
+/* What `minigrep serve` listens on. Every search runs inside root. */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Serve {
+    pub root: String,
+    pub port: u16,
+}
+
+/* Answers `GET /search?pattern=...` until the process is stopped. The parameters are:
+- pattern: what to search for, required.
+- mode: literal, regex, backtracking (-P) or boolean, literal when left out.
+- glob: only search files matching it, can be given more than once.
+- ignore_case: true to match regardless of case.
+The answer is streamed as JSON Lines, the same objects --json prints, followed by an
+`{"error": ...}` line for every path that could not be searched and one `{"stats": ...}` line
+at the end. A search that cannot run at all only gets the error line. */
+pub fn serve(serve: &Serve) -> Result<(), Box<dyn Error>> {
+    /* Only reachable from this machine, there is no authentication. */
+    let listener = TcpListener::bind(("127.0.0.1", serve.port))?;
+    eprintln!("Listening on http://{}/search", listener.local_addr()?);
+    accept(listener, &serve.root);
+    Ok(())
+}
+
+/* Every connection gets a thread of its own, so one slow search does not hold up the rest.
+Past MAX_CONNECTIONS new ones are turned away, so clients cannot pile up threads. */
+fn accept(listener: TcpListener, root: &str) {
+    let open = Arc::new(AtomicUsize::new(0));
+    for stream in listener.incoming() {
+        let Ok(mut stream) = stream else {
+            continue;
+        };
+        if open.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+            open.fetch_sub(1, Ordering::SeqCst);
+            let _ = write_error(&mut stream, "503 Service Unavailable", "too many searches at once, try again later");
+            continue;
+        }
+        let slot = Slot(Arc::clone(&open));
+        let root = root.to_string();
+        thread::spawn(move || {
+            let _slot = slot;
+            if let Err(e) = respond(stream, &root) {
+                eprintln!("Could not answer a request: {e}");
+            }
+        });
+    }
+}
+
+/* One of the MAX_CONNECTIONS, given back when its thread is done, even when it panics. */
+struct Slot(Arc<AtomicUsize>);
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+fn respond(mut stream: TcpStream, root: &str) -> Result<(), Box<dyn Error>> {
+    /* A client that stops sending or reading gets its connection closed instead of a thread. */
+    stream.set_read_timeout(Some(IDLE_TIMEOUT))?;
+    stream.set_write_timeout(Some(IDLE_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    if !read_line(&mut reader, &mut request_line)? {
+        return refuse(&mut stream, reader, "414 URI Too Long", "the request line is too long");
+    }
+    /* Nothing in the headers matters for a search, but they have to be read before answering. */
+    for count in 0.. {
+        let mut header = String::new();
+        if count == MAX_HEADERS || !read_line(&mut reader, &mut header)? {
+            return refuse(&mut stream, reader, "431 Request Header Fields Too Large", "the headers are too large");
+        }
+        if header.trim().is_empty() {
+            break;
+        }
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+    let (path, parameters) = target.split_once('?').unwrap_or((target, ""));
+    if method != "GET" {
+        return write_error(&mut stream, "405 Method Not Allowed", "only GET is supported");
+    }
+    if path != "/search" {
+        return write_error(&mut stream, "404 Not Found", "the only endpoint is /search");
+    }
+    let config = match build_config(parameters, root) {
+        Ok(config) => config,
+        Err(e) => return write_error(&mut stream, "400 Bad Request", &e),
+    };
+
+    stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Type: application/x-ndjson\r\nConnection: close\r\n\r\n")?;
+    let started = Instant::now();
+    let printer = Printer::new(config.output.clone(), Box::new(stream.try_clone()?));
+    let last = match search_with(&config, &printer) {
+        Ok(mut outcome) => {
+            for error in &outcome.errors {
+                stream.write_all(format!("{}\n", json!({ "error": error })).as_bytes())?;
+            }
+            outcome.stats.elapsed = started.elapsed();
+            format!("{{\"stats\":{}}}", outcome.stats.to_json())
+        }
+        Err(e) => json!({ "error": e.to_string() }).to_string(),
+    };
+    stream.write_all(format!("{last}\n").as_bytes())?;
+    Ok(())
+}
+
+/* Reads one line of the request into line, false when it is longer than MAX_LINE. */
+fn read_line(reader: &mut BufReader<TcpStream>, line: &mut String) -> io::Result<bool> {
+    let read = reader.by_ref().take(MAX_LINE).read_line(line)?;
+    Ok(read < MAX_LINE as usize || line.ends_with('\n'))
+}
+
+/* Answers a request that is too large. The rest of it is read and thrown away, up to a limit,
+because closing a connection with unread data makes the client lose the answer. */
+fn refuse(stream: &mut TcpStream, reader: BufReader<TcpStream>, status: &str, message: &str) -> Result<(), Box<dyn Error>> {
+    write_error(stream, status, message)?;
+    stream.shutdown(std::net::Shutdown::Write)?;
+    let _ = io::copy(&mut reader.take(1024 * 1024), &mut io::sink());
+    Ok(())
+}
+
+/* Turns the parameters into the arguments the command line would have, so a search over HTTP
+is checked and run exactly like one from the shell. */
+fn build_config(parameters: &str, root: &str) -> Result<Config, String> {
+    let mut pattern = None;
+    let mut ignore_case = false;
+    let mut args: Vec<String> = ["minigrep", "--json", "--sort", "path"].map(String::from).to_vec();
+
+    for pair in parameters.split('&').filter(|pair| !pair.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let value = percent_decode(value);
+        match percent_decode(key).as_str() {
+            "pattern" => pattern = Some(value),
+            "mode" => match value.as_str() {
+                "literal" => {}
+                "regex" => args.push(String::from("--regex")),
+                "backtracking" => args.push(String::from("-P")),
+                "boolean" => args.push(String::from("--boolean")),
+                _ => return Err(String::from("mode has to be literal, regex, backtracking or boolean")),
+            },
+            "glob" => args.extend([String::from("--glob"), value]),
+            "ignore_case" => ignore_case = value == "true",
+            key => return Err(format!("unknown parameter {key}")),
+        }
+    }
+
+    let pattern = pattern.ok_or("the pattern parameter is missing")?;
+    args.extend([String::from("--"), pattern, root.to_string()]);
+    let mut config = Config::build(&args)?;
+    /* The server's own environment should not decide this for every client. */
+    config.ignore_case = ignore_case;
+    Ok(config)
+}
+
+fn write_error(stream: &mut TcpStream, status: &str, message: &str) -> Result<(), Box<dyn Error>> {
+    let body = format!("{}\n", json!({ "error": message }));
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    Ok(())
+}
+
+/* Query strings write a space as + and anything else unusual as %XX. */
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        /* from_str_radix would take a sign too, like the +1 in %+1. */
+        let hex = bytes
+            .get(index + 1..index + 3)
+            .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|hex| std::str::from_utf8(hex).ok());
+        match (bytes[index], hex.and_then(|hex| u8::from_str_radix(hex, 16).ok())) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            (b'+', _) => {
+                decoded.push(b' ');
+                index += 1;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{Shutdown, SocketAddr};
+
+    fn start() -> SocketAddr {
+        let listener = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let address = listener.local_addr().unwrap();
+        let root = format!("{}/tests/fixtures", env!("CARGO_MANIFEST_DIR"));
+        thread::spawn(move || accept(listener, &root));
+        address
+    }
+
+    fn get(address: SocketAddr, target: &str) -> String {
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(stream, "GET {target} HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+        stream.shutdown(Shutdown::Write).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn searches_stream_json_lines() {
+        let address = start();
+        let response = get(address, "/search?pattern=NOBODY&ignore_case=true&glob=*.txt");
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        assert!(head.starts_with("HTTP/1.1 200 OK"));
+
+        let lines: Vec<serde_json::Value> = body.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        assert_eq!(3, lines.len());
+        assert_eq!("I'm nobody! Who are you?", lines[0]["text"]);
+        assert_eq!(2, lines[1]["line"]);
+        assert_eq!(2, lines[2]["stats"]["matches"]);
+    }
+
+    #[test]
+    fn bad_requests_get_an_error() {
+        let address = start();
+        assert!(get(address, "/search?mode=regex").starts_with("HTTP/1.1 400 Bad Request"));
+        assert!(get(address, "/search?pattern=%28&mode=regex").contains("\"error\":\"regex parse error"));
+        assert!(get(address, "/files").starts_with("HTTP/1.1 404 Not Found"));
+    }
+
+    #[test]
+    fn deep_and_large_requests_are_refused() {
+        let address = start();
+        let deep = get(address, &format!("/search?mode=boolean&pattern={}error", "%28".repeat(2000)));
+        assert!(deep.starts_with("HTTP/1.1 400 Bad Request"));
+        assert!(deep.contains("Query is nested too deeply"));
+
+        let long = get(address, &format!("/search?pattern={}", "a".repeat(20_000)));
+        assert!(long.starts_with("HTTP/1.1 414 URI Too Long"));
+        let headers = get(address, &format!("/search?pattern=x HTTP/1.1\r\nX-Long: {}\r\nX-End: ", "a".repeat(20_000)));
+        assert!(headers.starts_with("HTTP/1.1 431 Request Header Fields Too Large"));
+
+        /* The server is still there for the next request. */
+        assert!(get(address, "/search?pattern=nobody").starts_with("HTTP/1.1 200 OK"));
+    }
+
+    #[test]
+    fn decodes_query_strings() {
+        assert_eq!("error AND (disk)", percent_decode("error+AND+%28disk%29"));
+        assert_eq!("100%", percent_decode("100%"));
+        assert_eq!("% 1", percent_decode("%+1"));
+    }
+}
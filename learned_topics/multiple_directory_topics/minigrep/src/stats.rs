@@ -0,0 +1,80 @@
+use std::fmt;
+use std::time::Duration;
+
+use serde_json::json;
+
+/* Numbers about a single run, so audits can be compared over time. run returns them, and
+prints them after the results when --stats or --stats-json is given. */
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Stats {
+    pub files_searched: usize,
+    pub files_matched: usize,
+    pub lines_scanned: usize,
+    pub matches: usize,
+    pub bytes_read: u64,
+    pub elapsed: Duration,
+}
+
+/* How the stats should be printed, if at all. */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StatsOutput {
+    None,
+    Human,
+    Json,
+}
+
+impl Stats {
+    /* Adds up the numbers of two parts of the same run, like two threads. */
+    pub fn add(&mut self, other: &Stats) {
+        self.files_searched += other.files_searched;
+        self.files_matched += other.files_matched;
+        self.lines_scanned += other.lines_scanned;
+        self.matches += other.matches;
+        self.bytes_read += other.bytes_read;
+    }
+
+    pub fn to_json(&self) -> String {
+        json!({
+            "files_searched": self.files_searched,
+            "files_matched": self.files_matched,
+            "lines_scanned": self.lines_scanned,
+            "matches": self.matches,
+            "bytes_read": self.bytes_read,
+            "elapsed_seconds": self.elapsed.as_secs_f64(),
+        })
+        .to_string()
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{} files searched", self.files_searched)?;
+        writeln!(f, "{} files contained matches", self.files_matched)?;
+        writeln!(f, "{} lines scanned", self.lines_scanned)?;
+        writeln!(f, "{} matches", self.matches)?;
+        writeln!(f, "{} bytes read", self.bytes_read)?;
+        write!(f, "{:.6} seconds elapsed", self.elapsed.as_secs_f64())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_has_every_number() {
+        let stats = Stats {
+            files_searched: 2,
+            files_matched: 1,
+            lines_scanned: 9,
+            matches: 3,
+            bytes_read: 120,
+            elapsed: Duration::from_millis(1500),
+        };
+
+        let value: serde_json::Value = serde_json::from_str(&stats.to_json()).unwrap();
+        assert_eq!(2, value["files_searched"]);
+        assert_eq!(3, value["matches"]);
+        assert_eq!(1.5, value["elapsed_seconds"]);
+    }
+}
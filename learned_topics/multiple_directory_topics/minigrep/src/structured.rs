@@ -0,0 +1,334 @@
+use std::error::Error;
+
+use serde_json::Value;
+
+use crate::contains;
+use crate::matcher::Matcher;
+
+/* The formats minigrep knows how to split into records. Every record has named fields,
+a CSV record gets its names from the header row and a JSON Lines record is one JSON value
+per line. */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Csv,
+    JsonLines,
+}
+
+impl Format {
+    pub fn parse(name: &str) -> Result<Format, &'static str> {
+        match name {
+            "csv" => Ok(Format::Csv),
+            "jsonl" => Ok(Format::JsonLines),
+            _ => Err("Unknown format, expected csv or jsonl"),
+        }
+    }
+}
+
+/* A path to one field inside a record. For CSV this is always a single column name,
+for JSON Lines it can walk into nested objects and arrays, like `.user.id` or `.tags.0`. */
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldPath {
+    text: String,
+    segments: Vec<String>,
+}
+
+impl FieldPath {
+    /* `--field level` names a field directly. */
+    pub fn field(name: &str) -> Result<FieldPath, &'static str> {
+        if name.is_empty() {
+            return Err("Field name cannot be empty");
+        }
+        Ok(FieldPath {
+            text: name.to_string(),
+            segments: vec![name.to_string()],
+        })
+    }
+
+    /* `--jq-like .user.id` walks down the record one segment at a time. */
+    pub fn jq_like(path: &str) -> Result<FieldPath, &'static str> {
+        let rest = path
+            .strip_prefix('.')
+            .ok_or("A --jq-like path has to start with a dot")?;
+        let segments: Vec<String> = rest.split('.').map(String::from).collect();
+        if segments.iter().any(|segment| segment.is_empty()) {
+            return Err("A --jq-like path cannot contain empty segments");
+        }
+        Ok(FieldPath {
+            text: path.to_string(),
+            segments,
+        })
+    }
+
+    fn lookup<'v>(&self, value: &'v Value) -> Option<&'v Value> {
+        let mut current = value;
+        for segment in &self.segments {
+            current = match current {
+                Value::Object(map) => map.get(segment)?,
+                Value::Array(items) => items.get(segment.parse::<usize>().ok()?)?,
+                _ => return None,
+            };
+        }
+        Some(current)
+    }
+
+    fn column(&self, headers: &csv::StringRecord) -> Result<usize, Box<dyn Error>> {
+        let name = match self.segments.as_slice() {
+            [name] => name,
+            _ => return Err(format!("CSV columns cannot be nested: {}", self.text).into()),
+        };
+        headers
+            .iter()
+            .position(|header| header == name)
+            .ok_or_else(|| format!("No column named {name}").into())
+    }
+}
+
+/* A `--field name=value` filter. With `=` the field has to be the value, with `~=` it only has
+to contain it. */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Condition {
+    pub path: FieldPath,
+    pub value: String,
+    pub substring: bool,
+}
+
+impl Condition {
+    /* The name as it was written before the `=`, a `~` at its end asks for a substring. */
+    pub fn new(name: &str, value: &str) -> Result<Condition, &'static str> {
+        let (name, substring) = match name.strip_suffix('~') {
+            Some(name) => (name, true),
+            None => (name, false),
+        };
+        Ok(Condition {
+            path: FieldPath::field(name)?,
+            value: value.to_string(),
+            substring,
+        })
+    }
+
+    fn holds(&self, text: &str, ignore_case: bool) -> bool {
+        match (self.substring, ignore_case) {
+            (true, _) => contains(text, &self.value, ignore_case),
+            (false, true) => text.to_lowercase() == self.value.to_lowercase(),
+            (false, false) => text == self.value,
+        }
+    }
+}
+
+/* Everything needed to search inside records instead of inside plain lines.
+- selector: the field the matcher has to match, the whole record when there is none.
+- conditions: `--field name=value` filters, every one of them has to match as well.
+- columns: the fields to print, the whole record is printed when this is empty. */
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructuredSearch {
+    pub format: Format,
+    pub selector: Option<FieldPath>,
+    pub conditions: Vec<Condition>,
+    pub columns: Vec<FieldPath>,
+}
+
+impl StructuredSearch {
+    /* ignore_case is for the --field conditions, the matcher already knows. */
+    pub fn search(
+        &self,
+        matcher: &dyn Matcher,
+        contents: &str,
+        ignore_case: bool,
+    ) -> Result<Vec<String>, Box<dyn Error>> {
+        match self.format {
+            Format::Csv => self.search_csv(matcher, contents, ignore_case),
+            Format::JsonLines => self.search_json_lines(matcher, contents, ignore_case),
+        }
+    }
+
+    fn search_json_lines(
+        &self,
+        matcher: &dyn Matcher,
+        contents: &str,
+        ignore_case: bool,
+    ) -> Result<Vec<String>, Box<dyn Error>> {
+        let mut results = Vec::new();
+
+        for (index, line) in contents.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record: Value = serde_json::from_str(line)
+                .map_err(|e| format!("line {}: {e}", index + 1))?;
+
+            let field_matches = |condition: &Condition| {
+                condition
+                    .path
+                    .lookup(&record)
+                    .map(|value| condition.holds(&json_text(value), ignore_case))
+                    .unwrap_or(false)
+            };
+            let selected = match &self.selector {
+                Some(path) => match path.lookup(&record) {
+                    Some(value) => matcher.is_match(&json_text(value))?,
+                    None => false,
+                },
+                None => matcher.is_match(line)?,
+            };
+            if !selected || !self.conditions.iter().all(field_matches) {
+                continue;
+            }
+
+            if self.columns.is_empty() {
+                results.push(line.to_string());
+            } else {
+                let values: Vec<Value> = self
+                    .columns
+                    .iter()
+                    .map(|path| path.lookup(&record).cloned().unwrap_or(Value::Null))
+                    .collect();
+                results.push(Value::Array(values).to_string());
+            }
+        }
+
+        Ok(results)
+    }
+
+    fn search_csv(
+        &self,
+        matcher: &dyn Matcher,
+        contents: &str,
+        ignore_case: bool,
+    ) -> Result<Vec<String>, Box<dyn Error>> {
+        let mut reader = csv::Reader::from_reader(contents.as_bytes());
+        let headers = reader.headers()?.clone();
+
+        /* Resolve every name to a column index once, instead of once per record. */
+        let selector = self.selector.as_ref().map(|path| path.column(&headers)).transpose()?;
+        let mut conditions = Vec::new();
+        for condition in &self.conditions {
+            conditions.push((condition.path.column(&headers)?, condition));
+        }
+        let mut columns = Vec::new();
+        for path in &self.columns {
+            columns.push(path.column(&headers)?);
+        }
+
+        let mut results = Vec::new();
+        let mut record = csv::StringRecord::new();
+
+        loop {
+            /* Remember where the record starts and ends, so the original text can be
+            printed as it was written, quotes and all. */
+            let start = reader.position().byte() as usize;
+            if !reader.read_record(&mut record)? {
+                break;
+            }
+            let end = reader.position().byte() as usize;
+            let text = contents[start..end].trim_end_matches(['\r', '\n']);
+
+            let field_matches = |column: usize, condition: &Condition| {
+                record
+                    .get(column)
+                    .map(|value| condition.holds(value, ignore_case))
+                    .unwrap_or(false)
+            };
+            let selected = match selector {
+                Some(column) => match record.get(column) {
+                    Some(value) => matcher.is_match(value)?,
+                    None => false,
+                },
+                None => matcher.is_match(text)?,
+            };
+            if !selected || !conditions.iter().all(|&(column, condition)| field_matches(column, condition)) {
+                continue;
+            }
+
+            if columns.is_empty() {
+                results.push(text.to_string());
+            } else {
+                let mut writer = csv::Writer::from_writer(Vec::new());
+                writer.write_record(columns.iter().map(|&column| record.get(column).unwrap_or("")))?;
+                let row = String::from_utf8(writer.into_inner()?)?;
+                results.push(row.trim_end_matches(['\r', '\n']).to_string());
+            }
+        }
+
+        Ok(results)
+    }
+}
+
+/* Strings are compared without their quotes, anything else as compact JSON. */
+fn json_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matcher::Literal;
+
+    fn search(format: Format) -> StructuredSearch {
+        StructuredSearch {
+            format,
+            selector: None,
+            conditions: Vec::new(),
+            columns: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn json_lines_selector_and_condition() {
+        let contents = "\
+{\"level\":\"error\",\"user\":{\"id\":7},\"msg\":\"disk full\"}
+{\"level\":\"info\",\"user\":{\"id\":7},\"msg\":\"disk ok\"}
+{\"level\":\"error\",\"user\":{\"id\":8},\"msg\":\"user 7 logged out\"}";
+
+        let mut structured = search(Format::JsonLines);
+        structured.selector = Some(FieldPath::jq_like(".user.id").unwrap());
+        structured.conditions.push(Condition::new("level", "error").unwrap());
+
+        assert_eq!(
+            vec!["{\"level\":\"error\",\"user\":{\"id\":7},\"msg\":\"disk full\"}"],
+            structured.search(&Literal::new("7", false), contents, false).unwrap()
+        );
+
+        /* `=` wants the whole value, only `~=` is happy with a part of it. */
+        structured.conditions = vec![Condition::new("level", "err").unwrap()];
+        assert!(structured.search(&Literal::new("7", false), contents, false).unwrap().is_empty());
+        structured.conditions = vec![Condition::new("level~", "err").unwrap()];
+        assert_eq!(1, structured.search(&Literal::new("7", false), contents, false).unwrap().len());
+
+        structured.columns = vec![FieldPath::field("msg").unwrap()];
+        assert_eq!(
+            vec!["[\"disk full\"]"],
+            structured.search(&Literal::new("7", false), contents, false).unwrap()
+        );
+    }
+
+    #[test]
+    fn csv_selected_columns() {
+        let contents = "\
+name,city,note
+Ann,Utrecht,\"likes Rust, a lot\"
+Bob,Rustenburg,none
+";
+
+        let mut structured = search(Format::Csv);
+        assert_eq!(
+            vec!["Ann,Utrecht,\"likes Rust, a lot\"", "Bob,Rustenburg,none"],
+            structured.search(&Literal::new("Rust", false), contents, false).unwrap()
+        );
+
+        structured.selector = Some(FieldPath::field("city").unwrap());
+        structured.columns = vec![FieldPath::field("note").unwrap(), FieldPath::field("name").unwrap()];
+        assert_eq!(
+            vec!["none,Bob"],
+            structured.search(&Literal::new("rust", true), contents, true).unwrap()
+        );
+    }
+
+    #[test]
+    fn jq_like_paths_need_a_leading_dot() {
+        assert!(FieldPath::jq_like("user.id").is_err());
+        assert!(FieldPath::jq_like(".user..id").is_err());
+    }
+}
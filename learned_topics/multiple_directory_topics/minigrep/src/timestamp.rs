@@ -0,0 +1,118 @@
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime};
+
+/* The window --since and --until leave open, only lines logged inside it are searched. --since
+is inclusive and --until is not, so 10:00 to 10:10 are ten minutes without overlap.
+
+Every line starts with its timestamp, or is a continuation of the last line that did, like
+the rest of a stack trace. Timestamps with a zone are compared in UTC, and times without one,
+including the window itself, are taken to be UTC as well. */
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeWindow {
+    since: Option<NaiveDateTime>,
+    until: Option<NaiveDateTime>,
+    /* A chrono format string from --time-format, used instead of the formats below. */
+    format: Option<String>,
+}
+
+/* The ISO 8601 styles applications write, with and without a zone. */
+const ZONED_FORMATS: &[&str] = &["%Y-%m-%dT%H:%M:%S%.f%#z", "%Y-%m-%d %H:%M:%S%.f%#z"];
+const NAIVE_FORMATS: &[&str] = &["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f"];
+/* syslog, like `Oct  1 10:02:11 host kernel: ...`. */
+const SYSLOG_FORMAT: &str = "%b %e %H:%M:%S";
+/* The common log format of Apache and nginx, like `127.0.0.1 - - [01/Oct/2026:10:11:00 +0000]`. */
+const COMMON_LOG_FORMAT: &str = "%d/%b/%Y:%H:%M:%S %z";
+
+impl TimeWindow {
+    pub fn build(since: Option<&str>, until: Option<&str>, format: Option<String>) -> Result<TimeWindow, &'static str> {
+        let since = since.map(parse_bound).transpose()?;
+        let until = until.map(parse_bound).transpose()?;
+        if let (Some(since), Some(until)) = (since, until) {
+            if since >= until {
+                return Err("--since has to be before --until");
+            }
+        }
+        Ok(TimeWindow { since, until, format })
+    }
+
+    pub fn contains(&self, time: NaiveDateTime) -> bool {
+        self.since.is_none_or(|since| time >= since) && self.until.is_none_or(|until| time < until)
+    }
+
+    /* The time at the start of the line, None for a continuation line. */
+    pub fn timestamp(&self, line: &str) -> Option<NaiveDateTime> {
+        if let Some(format) = &self.format {
+            return parse_prefix(line, format).or_else(|| self.parse_without_year(line, format));
+        }
+
+        ZONED_FORMATS
+            .iter()
+            .chain(NAIVE_FORMATS)
+            .find_map(|format| parse_prefix(line, format))
+            .or_else(|| self.parse_without_year(line, SYSLOG_FORMAT))
+            .or_else(|| {
+                /* The time comes after the client's address and name, between brackets. */
+                let start = line.find('[')?;
+                parse_prefix(&line[start + 1..], COMMON_LOG_FORMAT)
+            })
+    }
+
+    /* syslog leaves the year out, so it is taken from the window. That is wrong for a log that
+    runs over New Year, but a window is hardly ever that wide. */
+    fn parse_without_year(&self, line: &str, format: &str) -> Option<NaiveDateTime> {
+        let year = self.since.or(self.until)?.year();
+        parse_prefix(&format!("{year} {line}"), &format!("%Y {format}"))
+    }
+}
+
+/* Parses a time from the start of the text, whatever follows it does not matter. */
+fn parse_prefix(text: &str, format: &str) -> Option<NaiveDateTime> {
+    if let Ok((time, _)) = DateTime::parse_and_remainder(text, format) {
+        return Some(time.naive_utc());
+    }
+    NaiveDateTime::parse_and_remainder(text, format).ok().map(|(time, _)| time)
+}
+
+/* --since and --until take a date, a date and a time, or a full RFC 3339 timestamp. */
+fn parse_bound(text: &str) -> Result<NaiveDateTime, &'static str> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(text) {
+        return Ok(time.naive_utc());
+    }
+    for format in ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%d %H:%M"] {
+        if let Ok(time) = NaiveDateTime::parse_from_str(text, format) {
+            return Ok(time);
+        }
+    }
+    NaiveDate::parse_from_str(text, "%Y-%m-%d")
+        .map(|date| date.and_time(Default::default()))
+        .map_err(|_| "--since and --until take a time like 2026-10-01T10:00")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(text: &str) -> Option<NaiveDateTime> {
+        Some(parse_bound(text).unwrap())
+    }
+
+    #[test]
+    fn finds_the_leading_timestamp() {
+        let window = TimeWindow::build(Some("2026-10-01"), None, None).unwrap();
+        assert_eq!(time("2026-10-01T08:05"), window.timestamp("2026-10-01T10:05:00+02:00 ERROR disk full"));
+        assert_eq!(time("2026-10-01T10:09:59.5"), window.timestamp("2026-10-01 10:09:59.500 WARN slow"));
+        assert_eq!(time("2026-10-01T10:02:11"), window.timestamp("Oct  1 10:02:11 host kernel: link down"));
+        assert_eq!(
+            time("2026-10-01T10:03"),
+            window.timestamp("127.0.0.1 - - [01/Oct/2026:12:03:00 +0200] \"GET / HTTP/1.1\" 500 0")
+        );
+        assert_eq!(None, window.timestamp("    at worker::run (src/worker.rs:10)"));
+    }
+
+    #[test]
+    fn since_is_inclusive_and_until_is_not() {
+        let window = TimeWindow::build(Some("2026-10-01T10:00"), Some("2026-10-01T10:10"), None).unwrap();
+        assert!(window.contains(time("2026-10-01T10:00").unwrap()));
+        assert!(!window.contains(time("2026-10-01T10:10").unwrap()));
+        assert!(TimeWindow::build(Some("2026-10-01T10:10"), Some("2026-10-01T10:00"), None).is_err());
+    }
+}
@@ -0,0 +1,241 @@
+use std::fs::{self, Metadata};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Instant, SystemTime};
+
+use crate::input::Source;
+
+/* The order --sort and --sortr put files in. */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortKey {
+    Path,
+    Modified,
+    Accessed,
+    Created,
+}
+
+impl SortKey {
+    pub fn parse(name: &str) -> Result<SortKey, &'static str> {
+        match name {
+            "path" => Ok(SortKey::Path),
+            "modified" => Ok(SortKey::Modified),
+            "accessed" => Ok(SortKey::Accessed),
+            "created" => Ok(SortKey::Created),
+            _ => Err("Unknown sort key, expected path, modified, accessed or created"),
+        }
+    }
+}
+
+/* Limits on how deep and into what sources() walks.
+- max_depth: how many directories down to go, 1 only searches the files in the given directories.
+- follow: follow symbolic links, links that lead back into a directory being walked are skipped.
+- one_file_system: do not walk into directories on another file system, like a mounted share. */
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WalkOptions {
+    pub max_depth: Option<usize>,
+    pub follow: bool,
+    pub one_file_system: bool,
+}
+
+/* What sources() found. A path that could not be read does not stop the walk, it ends up in
+errors instead. timed_out is set when the deadline passed before the walk was done. */
+#[derive(Debug, Default)]
+pub struct Walked {
+    pub sources: Vec<Source>,
+    pub errors: Vec<String>,
+    pub timed_out: bool,
+}
+
+/* Turns the paths from the command line into the files to search. Directories are searched
+recursively, but hidden files and directories (starting with a dot) inside them are skipped.
+A - stands for standard input. */
+pub fn sources(paths: &[String], options: &WalkOptions, deadline: Option<Instant>) -> Walked {
+    let mut walker = Walker { options, deadline, walked: Walked::default(), ancestors: Vec::new() };
+    for path in paths {
+        if path == "-" {
+            walker.walked.sources.push(Source::named(path));
+            continue;
+        }
+        match fs::metadata(path) {
+            Ok(metadata) if metadata.is_dir() => walker.walk(Path::new(path), 1, device(&metadata)),
+            Ok(_) => walker.walked.sources.push(Source::named(path)),
+            Err(e) => walker.walked.errors.push(format!("{path}: {e}")),
+        }
+    }
+    walker.walked
+}
+
+struct Walker<'a> {
+    options: &'a WalkOptions,
+    deadline: Option<Instant>,
+    walked: Walked,
+    /* The real paths of the directories being walked, to notice a link back into one of them. */
+    ancestors: Vec<PathBuf>,
+}
+
+impl Walker<'_> {
+    /* depth is the depth of the entries inside dir, the paths from the command line are at 0.
+    file_system is the device dir is on, when --one-file-system needs to know. */
+    fn walk(&mut self, dir: &Path, depth: usize, file_system: Option<u64>) {
+        if self.options.max_depth.is_some_and(|max| depth > max) {
+            return;
+        }
+        if self.deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            self.walked.timed_out = true;
+            return;
+        }
+
+        if self.options.follow {
+            match fs::canonicalize(dir) {
+                Ok(real) if self.ancestors.contains(&real) => {
+                    self.walked.errors.push(format!("{}: symbolic link loop, not followed", dir.display()));
+                    return;
+                }
+                Ok(real) => self.ancestors.push(real),
+                Err(e) => {
+                    self.walked.errors.push(format!("{}: {e}", dir.display()));
+                    return;
+                }
+            }
+        }
+
+        if let Err(e) = self.entries(dir, depth, file_system) {
+            self.walked.errors.push(format!("{}: {e}", dir.display()));
+        }
+
+        if self.options.follow {
+            self.ancestors.pop();
+        }
+    }
+
+    fn entries(&mut self, dir: &Path, depth: usize, file_system: Option<u64>) -> io::Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+            let path = entry.path();
+            /* Without --follow, symlink_metadata does not follow symbolic links, so a link to a
+            directory cannot send us around in circles. */
+            let metadata = match self.options.follow {
+                true => fs::metadata(&path),
+                false => fs::symlink_metadata(&path),
+            };
+            let metadata = match metadata {
+                Ok(metadata) => metadata,
+                Err(e) => {
+                    self.walked.errors.push(format!("{}: {e}", path.display()));
+                    continue;
+                }
+            };
+
+            if metadata.is_dir() {
+                if self.options.one_file_system && device(&metadata) != file_system {
+                    continue;
+                }
+                self.walk(&path, depth + 1, file_system);
+            } else if metadata.is_file() {
+                self.walked.sources.push(Source::found(&path.display().to_string()));
+            }
+        }
+        Ok(())
+    }
+}
+
+/* The file system a file is on. Only Unix says, elsewhere --one-file-system has no effect. */
+#[cfg(unix)]
+fn device(metadata: &Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.dev())
+}
+
+#[cfg(not(unix))]
+fn device(_metadata: &Metadata) -> Option<u64> {
+    None
+}
+
+pub fn sort(sources: &mut [Source], key: SortKey, reverse: bool) {
+    match key {
+        SortKey::Path => sources.sort_by(|a, b| a.path.cmp(&b.path)),
+        /* Files without the time, like blobs from a commit, come first. */
+        _ => sources.sort_by_cached_key(|source| (file_time(source, key), source.path.clone())),
+    }
+    if reverse {
+        sources.reverse();
+    }
+}
+
+fn file_time(source: &Source, key: SortKey) -> Option<SystemTime> {
+    if source.blob.is_some() {
+        return None;
+    }
+    let metadata = fs::metadata(&source.path).ok()?;
+    match key {
+        SortKey::Modified => metadata.modified().ok(),
+        SortKey::Accessed => metadata.accessed().ok(),
+        SortKey::Created => metadata.created().ok(),
+        SortKey::Path => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn walks_directories_and_skips_hidden_files() {
+        let paths = ["src".to_string(), "poem.txt".to_string()];
+        let mut sources = sources(&paths, &WalkOptions::default(), None).sources;
+        sort(&mut sources, SortKey::Path, false);
+        let paths: Vec<&str> = sources.iter().map(|source| source.path.as_str()).collect();
+
+        assert_eq!("poem.txt", paths[0]);
+        assert!(paths.contains(&"src/lib.rs"));
+        assert!(sources[0].named);
+        assert!(!sources[1].named);
+    }
+
+    #[test]
+    fn max_depth_and_missing_paths() {
+        let paths = ["tests".to_string(), "missing".to_string()];
+        let options = WalkOptions { max_depth: Some(1), ..WalkOptions::default() };
+        let walked = sources(&paths, &options, None);
+
+        assert!(walked.sources.iter().any(|source| source.path == "tests/cli.rs"));
+        assert!(!walked.sources.iter().any(|source| source.path.starts_with("tests/fixtures")));
+        assert_eq!(1, walked.errors.len());
+        assert!(walked.errors[0].starts_with("missing: "));
+    }
+
+    /* /proc is a file system of its own on Linux, a link to it leads onto another device. */
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn one_file_system_stays_off_other_devices() {
+        let dir = std::env::temp_dir().join(format!("minigrep-walk-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("a.txt"), "nobody\n").unwrap();
+        std::os::unix::fs::symlink("/proc", dir.join("proc")).unwrap();
+        assert_ne!(device(&fs::metadata(&dir).unwrap()), device(&fs::metadata("/proc").unwrap()));
+
+        let paths = [dir.display().to_string()];
+        let options = WalkOptions { max_depth: Some(2), follow: true, one_file_system: false };
+        let everywhere = sources(&paths, &options, None).sources;
+        let options = WalkOptions { one_file_system: true, ..options };
+        let here = sources(&paths, &options, None).sources;
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(everywhere.iter().any(|source| source.path.contains("/proc/")));
+        let here: Vec<&str> = here.iter().map(|source| source.path.as_str()).collect();
+        assert_eq!(vec![dir.join("a.txt").display().to_string()], here);
+    }
+
+    #[test]
+    fn sortr_reverses_the_order() {
+        let mut sources = vec![Source::named("a"), Source::named("c"), Source::named("b")];
+        sort(&mut sources, SortKey::Path, true);
+        let paths: Vec<&str> = sources.iter().map(|source| source.path.as_str()).collect();
+
+        assert_eq!(vec!["c", "b", "a"], paths);
+    }
+}
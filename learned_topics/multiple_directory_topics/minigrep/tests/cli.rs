@@ -0,0 +1,122 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/* Runs the real minigrep binary once for every directory in tests/cases and compares what it
+printed with the golden files next to it:
+- cmd: the arguments, one per line (an empty line is an empty argument).
+- env: optional, KEY=VALUE lines with extra environment variables.
+- stdin: optional, what minigrep reads from standard input, nothing when it is missing.
+- setup: optional, a shell script that is run in a new, empty directory before minigrep runs
+  there instead of in the fixtures. $FIXTURES points to the fixtures, for copying them.
+- stdout, stderr and status: what the run is expected to produce.
+Every run starts in tests/fixtures, so the arguments can name the files in there.
+
+Run with UPDATE_GOLDEN=1 to write the current output as the new golden files, and then look
+at the diff before committing them. */
+#[test]
+fn golden_files() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests");
+    let update = env::var("UPDATE_GOLDEN").is_ok();
+
+    let mut cases: Vec<PathBuf> = fs::read_dir(root.join("cases"))
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.is_dir())
+        .collect();
+    cases.sort();
+
+    let mut failures = Vec::new();
+    for case in &cases {
+        let actual = run_case(case, &root.join("fixtures"));
+
+        for (name, output) in &actual {
+            let golden = case.join(name);
+            if update {
+                fs::write(&golden, output).unwrap();
+                continue;
+            }
+            let expected = fs::read_to_string(&golden).unwrap_or_default();
+            if &expected != output {
+                failures.push(format!(
+                    "{}/{name}\n--- expected\n{expected}\n--- actual\n{output}",
+                    case.file_name().unwrap().to_string_lossy()
+                ));
+            }
+        }
+    }
+
+    assert!(failures.is_empty(), "{} golden file(s) differ:\n\n{}", failures.len(), failures.join("\n\n"));
+}
+
+fn run_case(case: &Path, fixtures: &Path) -> BTreeMap<&'static str, String> {
+    let cmd = fs::read_to_string(case.join("cmd")).unwrap();
+    let mut command = Command::new(env!("CARGO_BIN_EXE_minigrep"));
+    command.args(cmd.lines()).current_dir(fixtures).env_remove("IGNORE_CASE");
+    /* Cases that look at the history set it in their env file, the rest leave it alone. */
+    command.env("MINIGREP_HISTORY", "");
+
+    if case.join("setup").exists() {
+        let dir = env::temp_dir().join(format!("minigrep-golden-{}", case.file_name().unwrap().to_string_lossy()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let status = Command::new("sh")
+            .arg(case.join("setup"))
+            .current_dir(&dir)
+            .env("FIXTURES", fixtures)
+            .status()
+            .unwrap();
+        assert!(status.success(), "setup of {} failed", case.display());
+        command.current_dir(&dir);
+    }
+
+    if let Ok(vars) = fs::read_to_string(case.join("env")) {
+        for line in vars.lines() {
+            let (key, value) = line.split_once('=').unwrap();
+            command.env(key, value);
+        }
+    }
+
+    if let Ok(stdin) = fs::File::open(case.join("stdin")) {
+        command.stdin(Stdio::from(stdin));
+    }
+
+    let output = command.output().unwrap();
+    let status = match output.status.code() {
+        Some(code) => format!("{code}\n"),
+        None => "killed by a signal\n".to_string(),
+    };
+
+    BTreeMap::from([
+        ("stdout", normalize(&String::from_utf8_lossy(&output.stdout))),
+        ("stderr", normalize(&String::from_utf8_lossy(&output.stderr))),
+        ("status", status),
+    ])
+}
+
+/* Elapsed times differ on every run, so they are replaced before comparing. Everything else is
+kept as it was printed, a \r at the end of a line and a missing newline at the end included. */
+fn normalize(output: &str) -> String {
+    let mut normalized = String::new();
+    for full_line in output.split_inclusive('\n') {
+        let line = full_line.strip_suffix('\n').unwrap_or(full_line);
+        if line.ends_with(" seconds elapsed") {
+            normalized.push_str("<elapsed> seconds elapsed");
+        } else if let Some(start) = line.find("\"elapsed_seconds\":") {
+            let value_start = start + "\"elapsed_seconds\":".len();
+            let value_end = line[value_start..]
+                .find([',', '}'])
+                .map(|end| value_start + end)
+                .unwrap_or(line.len());
+            normalized.push_str(&line[..value_start]);
+            normalized.push_str("\"<elapsed>\"");
+            normalized.push_str(&line[value_end..]);
+        } else {
+            normalized.push_str(line);
+        }
+        normalized.push_str(&full_line[line.len()..]);
+    }
+    normalized
+}
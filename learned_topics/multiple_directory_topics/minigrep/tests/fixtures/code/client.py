@@ -0,0 +1,4 @@
+URL = "https://example.com/api"  # TODO: read from the config
+"""TODO in a docstring counts as a string"""
+def fetch(todo):
+    return todo
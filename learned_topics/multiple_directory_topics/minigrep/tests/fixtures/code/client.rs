@@ -0,0 +1,6 @@
+/* TODO: retry on timeouts, see https://example.com/issues/7 */
+const URL: &str = "https://example.com/api"; // TODO: read from the config
+fn fetch<'a>(todo: &'a str) -> &'a str {
+    let quote = '"';
+    todo
+}
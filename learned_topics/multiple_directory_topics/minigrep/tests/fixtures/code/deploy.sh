@@ -0,0 +1,4 @@
+#!/bin/sh
+# TODO: stop hard-coding the host
+curl "https://example.com/deploy" # TODO: add a token
+echo "$#" TODO-not-a-comment
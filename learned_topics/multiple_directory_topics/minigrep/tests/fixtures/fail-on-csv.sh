@@ -0,0 +1,5 @@
+#!/bin/sh
+case "$1" in
+  *.csv) echo "cannot convert $1" >&2; exit 3 ;;
+esac
+cat "$1"
@@ -0,0 +1,59 @@
+use minigrep::matcher::{Backtracking, Literal, Matcher, Regex, DEFAULT_BACKTRACK_LIMIT};
+use minigrep::{search, search_case_insensitive};
+use proptest::prelude::*;
+
+/* Properties that have to hold for any query and any text, not just for the poem. The
+fuzz targets in fuzz/ look for crashes, these look for wrong answers. */
+proptest! {
+    #[test]
+    fn every_result_contains_the_query(query in "\\PC{0,4}", contents in "(\\PC{0,20}\n){0,8}") {
+        for line in search(&query, &contents) {
+            prop_assert!(line.contains(&query));
+        }
+    }
+
+    #[test]
+    fn ignoring_case_never_finds_fewer_lines(query in "\\PC{0,4}", contents in "(\\PC{0,20}\n){0,8}") {
+        let found = search(&query, &contents);
+        let found_ignoring_case = search_case_insensitive(&query, &contents);
+        for line in found {
+            prop_assert!(found_ignoring_case.contains(&line));
+        }
+    }
+
+    #[test]
+    fn matches_are_the_query_in_the_line(query in "\\PC{1,4}", line in "\\PC{0,40}") {
+        for range in Literal::new(&query, false).find_iter(&line).unwrap() {
+            prop_assert_eq!(&line[range], query.as_str());
+        }
+    }
+
+    #[test]
+    fn ignore_case_ranges_are_char_boundaries(query in "\\PC{1,4}", line in "\\PC{0,40}") {
+        for range in Literal::new(&query, true).find_iter(&line).unwrap() {
+            prop_assert!(line.is_char_boundary(range.start) && line.is_char_boundary(range.end));
+        }
+    }
+
+    #[test]
+    fn escaped_regex_agrees_with_literal(query in "\\PC{1,4}", line in "\\PC{0,40}") {
+        let regex = Regex::new(&regex::escape(&query), false).unwrap();
+        prop_assert_eq!(Literal::new(&query, false).find_iter(&line).unwrap(), regex.find_iter(&line).unwrap());
+    }
+
+    #[test]
+    fn backtracking_agrees_with_the_default_engine(query in "\\PC{1,4}", line in "\\PC{0,40}") {
+        let pattern = regex::escape(&query);
+        let regex = Regex::new(&pattern, false).unwrap();
+        let backtracking = Backtracking::new(&pattern, false, DEFAULT_BACKTRACK_LIMIT).unwrap();
+        prop_assert_eq!(regex.find_iter(&line).unwrap(), backtracking.find_iter(&line).unwrap());
+    }
+
+    /* Lowercasing and the regex crate's case folding disagree on a few characters, like İ
+    which lowercases to two characters, so this one sticks to ASCII. */
+    #[test]
+    fn escaped_regex_agrees_with_literal_ignoring_case(query in "[ -~]{1,4}", line in "[ -~]{0,40}") {
+        let regex = Regex::new(&regex::escape(&query), true).unwrap();
+        prop_assert_eq!(Literal::new(&query, true).find_iter(&line).unwrap(), regex.find_iter(&line).unwrap());
+    }
+}